@@ -4,6 +4,7 @@ use crate::{
     game::{packets, packets::packet::Data},
     network_state::NetworkState,
 };
+use coas_transport::{Handshake, Role, Session};
 use enet::*;
 use log::{error, warn};
 use prost::Message;
@@ -19,6 +20,8 @@ pub struct Client {
     pub state: NetworkState,
     pub packet: Option<Vec<u8>>,
     foo: Dm,
+    handshake: Option<Handshake>,
+    session: Option<Session>,
     timer: Instant,
 }
 
@@ -38,6 +41,8 @@ impl Client {
             state: NetworkState::Unconnected,
             packet: None,
             timer: Instant::now(),
+            handshake: None,
+            session: None,
             foo: Dm::new(
                 String::new(),
                 String::from("KcBkgRw_ju2TbjHc9V21VY9-bm0U2mRAKPZdM9aKZ_E="),
@@ -50,10 +55,11 @@ impl Client {
             pac.data = Some(data);
             let mut buf = vec![];
             pac.encode(&mut buf)?;
-            let p = match Packet::new(
-                self.foo.encrypto(buf.as_slice()).as_bytes(),
-                PacketMode::ReliableSequenced,
-            ) {
+            let bytes = match self.session.as_mut() {
+                Some(session) => session.seal(&buf)?,
+                None => self.foo.encrypto(buf.as_slice()).into_bytes(),
+            };
+            let p = match Packet::new(&bytes, PacketMode::ReliableSequenced) {
                 Ok(p) => p,
                 Err(e) => {
                     warn!("{}", e);
@@ -78,16 +84,51 @@ impl Client {
         if let Some(recv) = pac {
             match recv {
                 Event::Receive { ref packet, .. } => {
-                    if let Ok(data) = self.foo.decrypto(packet.data()) {
-                        self.packet = Some(data);
+                    if let Some(handshake) = self.handshake.take() {
+                        match handshake.finish(packet.data(), Role::Client) {
+                            Ok(session) => {
+                                self.session = Some(session);
+                                self.state = NetworkState::RawConnection;
+                            }
+                            Err(e) => {
+                                error!("Handshake failed: {}", e);
+                                self.state = NetworkState::Unconnected;
+                                if let Some(mut peer) = self.peer.take() {
+                                    peer.disconnect(0);
+                                }
+                            }
+                        }
+                        return Ok(());
+                    }
+                    let data = match self.session.as_mut() {
+                        Some(session) => session.open(packet.data()).map_err(anyhow::Error::from),
+                        None => self.foo.decrypto(packet.data()),
+                    };
+                    match data {
+                        Ok(data) => self.packet = Some(data),
+                        Err(e) => warn!("{}", e),
                     }
                 }
                 Event::Connect(ref peer) => {
-                    self.state = NetworkState::RawConnection;
-                    self.peer = Some(peer.clone());
+                    let mut peer = peer.clone();
+                    // Keys from an earlier connection must never be used again.
+                    self.session = None;
+                    self.handshake = None;
+                    if cfg!(feature = "fernet-transport") {
+                        self.state = NetworkState::RawConnection;
+                    } else {
+                        let handshake = Handshake::new();
+                        let hello = Packet::new(&handshake.hello(), PacketMode::ReliableSequenced)?;
+                        peer.send_packet(hello, 0)?;
+                        self.handshake = Some(handshake);
+                        self.state = NetworkState::Handshaking;
+                    }
+                    self.peer = Some(peer);
                 }
                 _ => {
                     self.state = NetworkState::Unconnected;
+                    self.session = None;
+                    self.handshake = None;
                     println!("Disconnect");
                 }
             }
@@ -471,9 +471,10 @@ impl Game {
                     client.send(Data::UseItem(use_item))?;
                 }
             } else {
-                self.player
-                    .map
-                    .check_teleport(self.player.x, self.player.y, ctx)?;
+                let teleport = packets::Teleport::default();
+                if let Some(client) = &mut ctx.client {
+                    client.send(Data::Teleport(teleport))?;
+                }
             }
         }
         if ctx.input.key_pressed(VirtualKeyCode::H) {
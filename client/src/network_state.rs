@@ -1,6 +1,8 @@
 #[derive(Eq, PartialEq, Hash)]
 pub enum NetworkState {
     Unconnected,
+    /// Connected and waiting for the server's half of the key exchange.
+    Handshaking,
     RawConnection,
     Connected,
     AwaitingResponse,
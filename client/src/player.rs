@@ -191,6 +191,15 @@ impl Player {
                     self.gravity_timer.restart();
                     self.gravity_range += 1;
                     self.y -= 1;
+                    if self.gravity_range < self.gravity_max {
+                        let mut move_packet = packets::Move::default();
+                        move_packet.x = Some(self.x.try_into()?);
+                        move_packet.y = Some(self.y.try_into()?);
+                        move_packet.silent = Some(true);
+                        if let Some(client) = ctx.client.as_mut() {
+                            client.send(packets::packet::Data::Move(move_packet))?;
+                        }
+                    }
                     if self.gravity_range >= self.gravity_max {
                         if self.current_tile().is_some() {
                             self.state = PlayerState::OnGround;
@@ -332,6 +341,14 @@ impl Player {
                     if let Some(client) = ctx.client.as_mut() {
                         client.send(Data::Move(move_packet))?;
                     }
+                } else {
+                    // Stepping off an edge, the server still needs to know where we are.
+                    let mut move_packet = packets::Move::default();
+                    move_packet.x = Some(self.x.try_into()?);
+                    move_packet.y = Some(self.y.try_into()?);
+                    if let Some(client) = ctx.client.as_mut() {
+                        client.send(Data::Move(move_packet))?;
+                    }
                 }
             }
         }
@@ -362,6 +379,14 @@ impl Player {
                     if let Some(client) = ctx.client.as_mut() {
                         client.send(Data::Move(move_packet))?;
                     }
+                } else {
+                    // Stepping off an edge, the server still needs to know where we are.
+                    let mut move_packet = packets::Move::default();
+                    move_packet.x = Some(self.x.try_into()?);
+                    move_packet.y = Some(self.y.try_into()?);
+                    if let Some(client) = ctx.client.as_mut() {
+                        client.send(Data::Move(move_packet))?;
+                    }
                 }
             }
         }
@@ -0,0 +1,57 @@
+//! Compares the tile lookups the grids used to do, a reverse scan over every
+//! area, with `RectIndex` on a large generated map.
+use coas_map::{Rect, RectIndex};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const WIDTH: isize = 10_000;
+
+/// A long map with a floor, platforms and ladders every few tiles and a few
+/// map-wide layers on top, about 7500 areas.
+fn large_map() -> Vec<(Rect, String)> {
+    let mut areas = vec![(Rect::new(0, WIDTH, 0, 0), "grass".to_string())];
+    for x in (0..WIDTH).step_by(4) {
+        let height = 1 + x % 37;
+        areas.push((Rect::new(x, x + 2, height, height), "concrete".to_string()));
+        areas.push((Rect::new(x + 3, x + 3, 0, height), "ladder".to_string()));
+        if x % 20 == 0 {
+            areas.push((Rect::new(x, x, 0, 50), "wall_metal".to_string()));
+        }
+    }
+    areas.push((Rect::new(0, WIDTH, 60, 60), "roof".to_string()));
+    areas
+}
+
+fn linear(areas: &[(Rect, String)], x: isize, y: isize) -> Option<&String> {
+    areas
+        .iter()
+        .rev()
+        .find(|(area, _)| area.contains(x, y))
+        .map(|(_, tile)| tile)
+}
+
+fn lookup(c: &mut Criterion) {
+    let areas = large_map();
+    let index = areas.iter().cloned().collect::<RectIndex<_>>();
+    let positions = (0..1000)
+        .map(|i| ((i * 7919) % WIDTH, i % 45))
+        .collect::<Vec<_>>();
+    let mut group = c.benchmark_group("get_tile");
+    group.bench_function("linear", |b| {
+        b.iter(|| {
+            for (x, y) in &positions {
+                black_box(linear(&areas, black_box(*x), black_box(*y)));
+            }
+        })
+    });
+    group.bench_function("rect_index", |b| {
+        b.iter(|| {
+            for (x, y) in &positions {
+                black_box(index.get(black_box(*x), black_box(*y)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lookup);
+criterion_main!(benches);
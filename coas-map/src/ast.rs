@@ -0,0 +1,126 @@
+use std::ops::RangeInclusive;
+
+use crate::index::RectIndex;
+
+/// An inclusive area of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub min_x: isize,
+    pub max_x: isize,
+    pub min_y: isize,
+    pub max_y: isize,
+}
+
+impl Rect {
+    pub fn new(min_x: isize, max_x: isize, min_y: isize, max_y: isize) -> Self {
+        Self {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+        }
+    }
+    pub fn contains(&self, x: isize, y: isize) -> bool {
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+}
+
+/// A teleporter destination coordinate, either fixed or picked at random from a range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Coordinate {
+    Fixed(isize),
+    Range(RangeInclusive<isize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Teleporter {
+    pub area: Rect,
+    pub x: Coordinate,
+    pub y: Coordinate,
+    pub map: String,
+}
+
+/// A single line of a map file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Directive {
+    /// `map name`
+    Map(String),
+    /// `maxx x`
+    MaxX(isize),
+    /// `maxy y`
+    MaxY(isize),
+    /// `tile minx maxx miny maxy tile`
+    Tile { area: Rect, tile: String },
+    /// `safe_zone minx maxx miny maxy`
+    SafeZone(Rect),
+    /// `zone minx maxx miny maxy text...`
+    Zone { area: Rect, text: String },
+    /// `ambience minx maxx miny maxy path [volume]`
+    Ambience {
+        area: Rect,
+        path: String,
+        volume: f64,
+    },
+    /// `teleporter minx maxx miny maxy x y map`, x and y may be `start...end`
+    Teleporter(Teleporter),
+    /// `items minx maxx miny maxy maximum spawn_time names...`
+    Items {
+        area: Rect,
+        maximum: isize,
+        spawn_time: u128,
+        names: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// 1-based line number the directive was read from.
+    pub line: usize,
+    pub directive: Directive,
+}
+
+/// A parsed map file, directives are kept in the order they were declared.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MapFile {
+    pub entries: Vec<Entry>,
+}
+
+impl MapFile {
+    pub fn directives(&self) -> impl Iterator<Item = &Directive> {
+        self.entries.iter().map(|e| &e.directive)
+    }
+    /// The declared name, the last `map` line wins.
+    pub fn name(&self) -> Option<&str> {
+        self.directives()
+            .filter_map(|d| match d {
+                Directive::Map(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .last()
+    }
+    pub fn max_x(&self) -> Option<isize> {
+        self.directives()
+            .filter_map(|d| match d {
+                Directive::MaxX(x) => Some(*x),
+                _ => None,
+            })
+            .last()
+    }
+    /// Every tile, indexed for lookups by position.
+    pub fn tiles(&self) -> RectIndex<&str> {
+        self.directives()
+            .filter_map(|d| match d {
+                Directive::Tile { area, tile } => Some((*area, tile.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+    pub fn max_y(&self) -> Option<isize> {
+        self.directives()
+            .filter_map(|d| match d {
+                Directive::MaxY(y) => Some(*y),
+                _ => None,
+            })
+            .last()
+    }
+}
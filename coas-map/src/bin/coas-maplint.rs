@@ -0,0 +1,67 @@
+//! Checks every map in a directory and reports problems before the server
+//! loads them.
+//!
+//! Usage: coas-maplint [--maps <dir>] [--client <dir>]
+//!
+//! `--maps` defaults to `maps`, `--client` is the client directory ambience
+//! paths are resolved against and defaults to `../client`. Exits with 1 if any
+//! map has errors.
+use std::{path::PathBuf, process::ExitCode};
+
+use coas_map::lint::{self, Linter, Problem};
+
+fn main() -> ExitCode {
+    let mut maps_dir = PathBuf::from("maps");
+    let mut client_dir = PathBuf::from("../client");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "--maps" => &mut maps_dir,
+            "--client" => &mut client_dir,
+            _ => {
+                eprintln!("Usage: coas-maplint [--maps <dir>] [--client <dir>]");
+                return ExitCode::from(2);
+            }
+        };
+        match args.next() {
+            Some(path) => *value = PathBuf::from(path),
+            None => {
+                eprintln!("{} needs a directory", arg);
+                return ExitCode::from(2);
+            }
+        }
+    }
+    let files = match lint::read_maps(&maps_dir) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Unable to read {}: {}", maps_dir.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+    let parsed = files
+        .iter()
+        .map(|(name, data)| (name, coas_map::parse(data)))
+        .collect::<Vec<_>>();
+    let mut linter = Linter::new().with_client_dir(client_dir);
+    for (name, file) in &parsed {
+        if let Ok(file) = file {
+            linter.add_map(name, file);
+        }
+    }
+    let mut failed = false;
+    for (name, file) in &parsed {
+        let problems = match file {
+            Ok(file) => linter.lint(file),
+            Err(e) => vec![Problem::from(e.clone())],
+        };
+        failed |= lint::has_errors(&problems);
+        for problem in problems {
+            println!("{}.map: {}", name, problem);
+        }
+    }
+    println!("Checked {} maps", parsed.len());
+    if failed {
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
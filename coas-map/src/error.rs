@@ -0,0 +1,26 @@
+use thiserror::Error;
+
+/// A line of a map file that couldn't be parsed.
+#[derive(Debug, Error, Clone, PartialEq)]
+#[error("line {line}: {kind}")]
+pub struct ParseError {
+    /// 1-based line number in the map file.
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ErrorKind {
+    #[error("unknown directive '{0}'")]
+    UnknownDirective(String),
+    #[error("{directive} expects {expected} arguments, found {found}")]
+    ArgumentCount {
+        directive: &'static str,
+        expected: &'static str,
+        found: usize,
+    },
+    #[error("{field} '{value}' is not a valid number")]
+    InvalidNumber { field: &'static str, value: String },
+    #[error("{field} '{value}' is not a valid range, expected start...end")]
+    InvalidRange { field: &'static str, value: String },
+}
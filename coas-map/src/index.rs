@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use crate::ast::Rect;
+
+/// Side length of a chunk, in tiles.
+const CHUNK_SIZE: isize = 16;
+/// Areas covering more chunks than this are checked on every lookup instead
+/// of being copied into each chunk, map-wide floors usually end up here.
+const MAX_CHUNKS: isize = 1024;
+
+/// Finds the last inserted area covering a position.
+///
+/// Maps declare areas on top of each other and the last one declared wins,
+/// so every lookup has to find the newest area containing the position.
+/// Areas are bucketed into fixed size chunks, and each chunk lists the areas
+/// overlapping it in insertion order, so a lookup only looks at the areas near
+/// the position plus the few very large ones.
+#[derive(Debug, Clone)]
+pub struct RectIndex<T> {
+    areas: Vec<(Rect, T)>,
+    chunks: HashMap<(isize, isize), Vec<usize>>,
+    large: Vec<usize>,
+}
+
+impl<T> Default for RectIndex<T> {
+    fn default() -> Self {
+        Self {
+            areas: vec![],
+            chunks: HashMap::new(),
+            large: vec![],
+        }
+    }
+}
+
+impl<T> RectIndex<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn insert(&mut self, area: Rect, value: T) {
+        let index = self.areas.len();
+        self.areas.push((area, value));
+        if area.min_x > area.max_x || area.min_y > area.max_y {
+            return;
+        }
+        let (min_cx, min_cy) = chunk(area.min_x, area.min_y);
+        let (max_cx, max_cy) = chunk(area.max_x, area.max_y);
+        let width = max_cx.saturating_sub(min_cx).saturating_add(1);
+        let height = max_cy.saturating_sub(min_cy).saturating_add(1);
+        if width.saturating_mul(height) > MAX_CHUNKS {
+            self.large.push(index);
+            return;
+        }
+        for cy in min_cy..=max_cy {
+            for cx in min_cx..=max_cx {
+                self.chunks.entry((cx, cy)).or_default().push(index);
+            }
+        }
+    }
+    /// The value of the last inserted area containing the position.
+    pub fn get(&self, x: isize, y: isize) -> Option<&T> {
+        let contains = |i: &&usize| self.areas[**i].0.contains(x, y);
+        let near = self
+            .chunks
+            .get(&chunk(x, y))
+            .and_then(|indices| indices.iter().rev().find(contains));
+        let large = self.large.iter().rev().find(contains);
+        let index = near.max(large)?;
+        Some(&self.areas[*index].1)
+    }
+    pub fn contains(&self, x: isize, y: isize) -> bool {
+        self.get(x, y).is_some()
+    }
+    /// Every area in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = &(Rect, T)> {
+        self.areas.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.areas.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.areas.is_empty()
+    }
+}
+
+impl<T> FromIterator<(Rect, T)> for RectIndex<T> {
+    fn from_iter<I: IntoIterator<Item = (Rect, T)>>(iter: I) -> Self {
+        let mut index = Self::new();
+        for (area, value) in iter {
+            index.insert(area, value);
+        }
+        index
+    }
+}
+
+fn chunk(x: isize, y: isize) -> (isize, isize) {
+    (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What the grids did before the index, the last declared area wins.
+    fn linear<T>(areas: &[(Rect, T)], x: isize, y: isize) -> Option<&T> {
+        areas
+            .iter()
+            .rev()
+            .find(|(area, _)| area.contains(x, y))
+            .map(|(_, value)| value)
+    }
+
+    #[test]
+    fn last_declared_area_wins() {
+        let areas = vec![
+            (Rect::new(0, 5000, 0, 0), "floor"),
+            (Rect::new(10, 20, 0, 10), "ladder"),
+            (Rect::new(15, 15, 0, 0), "wall"),
+            (Rect::new(-40, -1, -40, -1), "pit"),
+            (Rect::new(0, 5000, 0, 0), "grass"),
+            (Rect::new(12, 12, 5, 5), "metal"),
+            (Rect::new(30, 20, 0, 0), "inverted"),
+        ];
+        let index = areas.iter().cloned().collect::<RectIndex<_>>();
+        for y in -50..=50 {
+            for x in -50..=100 {
+                assert_eq!(index.get(x, y), linear(&areas, x, y), "at {}, {}", x, y);
+            }
+        }
+        assert_eq!(index.get(12, 5), Some(&"metal"));
+        assert_eq!(index.get(15, 0), Some(&"grass"));
+        assert_eq!(index.get(-1, -1), Some(&"pit"));
+        assert_eq!(index.get(25, 0), Some(&"grass"));
+        assert_eq!(index.len(), areas.len());
+    }
+
+    #[test]
+    fn huge_areas_do_not_fill_every_chunk() {
+        let mut index = RectIndex::new();
+        index.insert(Rect::new(isize::MIN, isize::MAX, 0, 0), 1);
+        index.insert(Rect::new(3, 3, 0, 0), 2);
+        assert_eq!(index.chunks.len(), 1);
+        assert_eq!(index.get(isize::MAX, 0), Some(&1));
+        assert_eq!(index.get(3, 0), Some(&2));
+        assert_eq!(index.get(3, 1), None);
+    }
+}
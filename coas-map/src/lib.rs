@@ -0,0 +1,18 @@
+//! Parser for the `.map` format shared by the client and the server.
+//!
+//! A map file is a list of directives, one per line. [`parse`] turns the whole
+//! file into a [`MapFile`] so both sides read exactly the same thing, and any
+//! line that can't be understood is reported with its line number instead of
+//! being skipped or panicking. The [`lint`] module checks a parsed map against the
+//! rest of the world, it backs the `coas-maplint` binary and the server's map
+//! loading.
+mod ast;
+mod error;
+mod index;
+pub mod lint;
+mod parser;
+
+pub use ast::{Coordinate, Directive, Entry, MapFile, Rect, Teleporter};
+pub use error::{ErrorKind, ParseError};
+pub use index::RectIndex;
+pub use parser::{parse, DEFAULT_AMBIENCE_VOLUME};
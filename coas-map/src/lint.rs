@@ -0,0 +1,294 @@
+//! Checks that go beyond the syntax of a single line.
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ast::{Coordinate, Directive, MapFile, Rect},
+    error::ParseError,
+    index::RectIndex,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The map loads, but something in it won't work as intended.
+    Warning,
+    /// The map must not be loaded.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub line: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Problem {
+    fn error(line: usize, message: String) -> Self {
+        Self {
+            line: Some(line),
+            severity: Severity::Error,
+            message,
+        }
+    }
+    fn warning(line: usize, message: String) -> Self {
+        Self {
+            line: Some(line),
+            severity: Severity::Warning,
+            message,
+        }
+    }
+}
+
+impl From<ParseError> for Problem {
+    fn from(error: ParseError) -> Self {
+        Self::error(error.line, error.kind.to_string())
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.line {
+            Some(line) => write!(f, "{}: line {}: {}", severity, line, self.message),
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+pub fn has_errors(problems: &[Problem]) -> bool {
+    problems.iter().any(|p| p.severity == Severity::Error)
+}
+
+/// The size of a map teleporters may lead to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bounds {
+    max_x: isize,
+    max_y: Option<isize>,
+}
+
+#[derive(Default)]
+pub struct Linter {
+    maps: HashMap<String, Bounds>,
+    client_dir: Option<PathBuf>,
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Ambience paths are relative to the client's directory, they are only
+    /// checked when it is known.
+    pub fn with_client_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.client_dir = Some(dir.into());
+        self
+    }
+    /// Registers a map teleporters can point at, `name` is the file name
+    /// without `.map`.
+    pub fn add_map(&mut self, name: &str, file: &MapFile) {
+        self.maps.insert(
+            name.to_string(),
+            Bounds {
+                max_x: file.max_x().unwrap_or_default(),
+                max_y: file.max_y(),
+            },
+        );
+    }
+    pub fn lint(&self, file: &MapFile) -> Vec<Problem> {
+        let mut problems = vec![];
+        let max_x = file.max_x();
+        let tiles = file.tiles();
+        for entry in &file.entries {
+            let line = entry.line;
+            match &entry.directive {
+                Directive::Tile { area, .. } => {
+                    check_area(&mut problems, line, "tile", area);
+                }
+                Directive::Zone { area, .. } => {
+                    check_area(&mut problems, line, "zone", area);
+                }
+                Directive::SafeZone(area) => {
+                    if check_area(&mut problems, line, "safe_zone", area) {
+                        let outside = match max_x {
+                            Some(max_x) => area.min_x < 0 || area.max_x > max_x,
+                            None => area.min_x < 0,
+                        };
+                        if outside || area.min_y < 0 {
+                            problems.push(Problem::error(
+                                line,
+                                "safe_zone reaches outside the map".to_string(),
+                            ));
+                        }
+                    }
+                }
+                Directive::Ambience { area, path, .. } => {
+                    check_area(&mut problems, line, "ambience", area);
+                    if let Some(dir) = &self.client_dir {
+                        if !dir.join(path).is_file() {
+                            problems.push(Problem::warning(
+                                line,
+                                format!("ambience file {} doesn't exist", path),
+                            ));
+                        }
+                    }
+                }
+                Directive::Teleporter(teleporter) => {
+                    check_area(&mut problems, line, "teleporter", &teleporter.area);
+                    let Some(bounds) = self.maps.get(&teleporter.map) else {
+                        problems.push(Problem::error(
+                            line,
+                            format!("teleporter leads to unknown map {}", teleporter.map),
+                        ));
+                        continue;
+                    };
+                    let x_valid = coordinate_within(&teleporter.x, 0, Some(bounds.max_x));
+                    let y_valid = coordinate_within(&teleporter.y, 0, bounds.max_y);
+                    if !x_valid || !y_valid {
+                        problems.push(Problem::error(
+                            line,
+                            format!(
+                                "teleporter destination is outside of map {}",
+                                teleporter.map
+                            ),
+                        ));
+                    }
+                }
+                Directive::Items { area, names, .. } => {
+                    if !check_area(&mut problems, line, "items", area) {
+                        continue;
+                    }
+                    if names.is_empty() {
+                        problems.push(Problem::error(line, "items has no item names".to_string()));
+                    }
+                    if let Some((x, y, reason)) = bad_spawn(&tiles, area) {
+                        problems.push(Problem::error(
+                            line,
+                            format!("items can spawn {} at {}, {}", reason, x, y),
+                        ));
+                    }
+                }
+                Directive::MaxX(x) | Directive::MaxY(x) => {
+                    if *x < 0 {
+                        problems.push(Problem::error(
+                            line,
+                            "the map size can't be negative".to_string(),
+                        ));
+                    }
+                }
+                Directive::Map(_) => {}
+            }
+        }
+        problems
+    }
+}
+
+/// Pushes an error and returns false if the area's minimum is above its maximum.
+fn check_area(problems: &mut Vec<Problem>, line: usize, directive: &str, area: &Rect) -> bool {
+    if area.min_x > area.max_x || area.min_y > area.max_y {
+        problems.push(Problem::error(
+            line,
+            format!("{} has its minimum above its maximum", directive),
+        ));
+        return false;
+    }
+    true
+}
+
+fn coordinate_within(coordinate: &Coordinate, min: isize, max: Option<isize>) -> bool {
+    let (start, end) = match coordinate {
+        Coordinate::Fixed(value) => (*value, *value),
+        Coordinate::Range(range) => (*range.start(), *range.end()),
+    };
+    start >= min && max.is_none_or(|max| end <= max)
+}
+
+/// Finds the first position in the area an item couldn't be picked up from,
+/// players can't walk into walls and fall through anything that isn't a tile
+/// above the ground.
+fn bad_spawn(tiles: &RectIndex<&str>, area: &Rect) -> Option<(isize, isize, &'static str)> {
+    for y in area.min_y..=area.max_y {
+        for x in area.min_x..=area.max_x {
+            match tiles.get(x, y) {
+                Some(tile) if tile.contains("wall") => return Some((x, y, "in a wall")),
+                None if y != 0 => return Some((x, y, "in mid-air")),
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// Reads every `.map` file in a directory, keyed by file name without `.map`.
+pub fn read_maps(dir: &Path) -> std::io::Result<Vec<(String, String)>> {
+    let mut maps = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("map") || !path.is_file() {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|n| n.to_str()) {
+            maps.push((name.to_string(), std::fs::read_to_string(&path)?));
+        }
+    }
+    maps.sort();
+    Ok(maps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn lint(data: &str) -> Vec<String> {
+        let file = parse(data).unwrap();
+        let mut linter = Linter::new();
+        linter.add_map("main", &file);
+        linter.lint(&file).iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn valid_map_has_no_problems() {
+        assert!(lint(
+            "map main
+            maxx 100
+            tile 0 100 0 0 grass
+            tile 10 12 1 5 ladder
+            safe_zone 0 10 0 5
+            items 0 20 0 0 3 1000 health_potion
+            teleporter 10 10 5 5 0...100 0 main"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn reports_each_problem() {
+        assert_eq!(
+            lint(
+                "map main
+                maxx 100
+                tile 0 100 0 0 grass
+                tile 50 50 0 0 wall_metal
+                tile 10 5 0 0 grass
+                safe_zone 90 110 0 0
+                teleporter 0 0 0 0 5 0 arena
+                teleporter 0 0 0 0 101 0 main
+                items 45 55 0 0 1 1000 health_potion
+                items 0 0 0 1 1 1000 health_potion"
+            ),
+            vec![
+                "error: line 5: tile has its minimum above its maximum",
+                "error: line 6: safe_zone reaches outside the map",
+                "error: line 7: teleporter leads to unknown map arena",
+                "error: line 8: teleporter destination is outside of map main",
+                "error: line 9: items can spawn in a wall at 50, 0",
+                "error: line 10: items can spawn in mid-air at 0, 1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use std::{ops::RangeInclusive, str::FromStr};
+
+use crate::{
+    ast::{Coordinate, Directive, Entry, MapFile, Rect, Teleporter},
+    error::{ErrorKind, ParseError},
+};
+
+/// The volume ambience plays at when the line doesn't give one.
+pub const DEFAULT_AMBIENCE_VOLUME: f64 = 0.8;
+
+/// Parses a whole map file, stopping at the first line that isn't valid.
+/// Blank lines are ignored.
+pub fn parse(data: &str) -> Result<MapFile, ParseError> {
+    let mut map = MapFile::default();
+    for (index, line) in data.lines().enumerate() {
+        let parsed = line.split_whitespace().collect::<Vec<&str>>();
+        if parsed.is_empty() {
+            continue;
+        }
+        let directive = parse_directive(&parsed).map_err(|kind| ParseError {
+            line: index + 1,
+            kind,
+        })?;
+        map.entries.push(Entry {
+            line: index + 1,
+            directive,
+        });
+    }
+    Ok(map)
+}
+
+fn parse_directive(parsed: &[&str]) -> Result<Directive, ErrorKind> {
+    let args = &parsed[1..];
+    let directive = match parsed[0] {
+        "map" => {
+            arguments("map", "at least 1", args, !args.is_empty())?;
+            Directive::Map(args[0].to_string())
+        }
+        "maxx" => {
+            arguments("maxx", "1", args, args.len() == 1)?;
+            Directive::MaxX(number("maxx", args[0])?)
+        }
+        "maxy" => {
+            arguments("maxy", "1", args, args.len() == 1)?;
+            Directive::MaxY(number("maxy", args[0])?)
+        }
+        "tile" => {
+            arguments("tile", "5", args, args.len() == 5)?;
+            Directive::Tile {
+                area: rect(args)?,
+                tile: args[4].to_string(),
+            }
+        }
+        "safe_zone" => {
+            arguments("safe_zone", "4", args, args.len() == 4)?;
+            Directive::SafeZone(rect(args)?)
+        }
+        "zone" => {
+            arguments("zone", "at least 5", args, args.len() >= 5)?;
+            Directive::Zone {
+                area: rect(args)?,
+                text: args[4..].join(" "),
+            }
+        }
+        "ambience" => {
+            arguments(
+                "ambience",
+                "5 or 6",
+                args,
+                args.len() == 5 || args.len() == 6,
+            )?;
+            let volume = match args.get(5) {
+                Some(volume) => number("volume", volume)?,
+                None => DEFAULT_AMBIENCE_VOLUME,
+            };
+            Directive::Ambience {
+                area: rect(args)?,
+                path: args[4].to_string(),
+                volume,
+            }
+        }
+        "teleporter" => {
+            arguments("teleporter", "7", args, args.len() == 7)?;
+            Directive::Teleporter(Teleporter {
+                area: rect(args)?,
+                x: coordinate("x", args[4])?,
+                y: coordinate("y", args[5])?,
+                map: args[6].to_string(),
+            })
+        }
+        "items" => {
+            arguments("items", "at least 7", args, args.len() >= 7)?;
+            Directive::Items {
+                area: rect(args)?,
+                maximum: number("maximum", args[4])?,
+                spawn_time: number("spawn_time", args[5])?,
+                names: args[6..].iter().map(|name| name.to_string()).collect(),
+            }
+        }
+        other => return Err(ErrorKind::UnknownDirective(other.to_string())),
+    };
+    Ok(directive)
+}
+
+fn arguments(
+    directive: &'static str,
+    expected: &'static str,
+    args: &[&str],
+    valid: bool,
+) -> Result<(), ErrorKind> {
+    if valid {
+        return Ok(());
+    }
+    Err(ErrorKind::ArgumentCount {
+        directive,
+        expected,
+        found: args.len(),
+    })
+}
+
+fn number<T: FromStr>(field: &'static str, value: &str) -> Result<T, ErrorKind> {
+    value.parse().map_err(|_| ErrorKind::InvalidNumber {
+        field,
+        value: value.to_string(),
+    })
+}
+
+fn rect(args: &[&str]) -> Result<Rect, ErrorKind> {
+    Ok(Rect::new(
+        number("minx", args[0])?,
+        number("maxx", args[1])?,
+        number("miny", args[2])?,
+        number("maxy", args[3])?,
+    ))
+}
+
+fn coordinate(field: &'static str, value: &str) -> Result<Coordinate, ErrorKind> {
+    if !value.contains("...") {
+        return Ok(Coordinate::Fixed(number(field, value)?));
+    }
+    range(field, value).map(Coordinate::Range)
+}
+
+fn range(field: &'static str, value: &str) -> Result<RangeInclusive<isize>, ErrorKind> {
+    let invalid = || ErrorKind::InvalidRange {
+        field,
+        value: value.to_string(),
+    };
+    let (start, end) = value.split_once("...").ok_or_else(invalid)?;
+    let start = start.parse::<isize>().map_err(|_| invalid())?;
+    let end = end.parse::<isize>().map_err(|_| invalid())?;
+    if start > end {
+        return Err(invalid());
+    }
+    Ok(start..=end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_directive() {
+        let map = parse(
+            "map main
+            maxx 200
+            maxy 50
+
+            tile 0 200 0 0 grass
+            safe_zone 0 10 0 5
+            zone 0 10 0 5 The town square
+            ambience 0 10 0 5 sounds/ambience/town.mp3
+            ambience 0 10 0 5 sounds/ambience/wind.mp3 0.5
+            teleporter 5 5 0 0 1...9 3 arena
+            items 0 200 1 1 5 10000 health_potion ammo_pistol",
+        )
+        .unwrap();
+        assert_eq!(map.name(), Some("main"));
+        assert_eq!(map.max_x(), Some(200));
+        assert_eq!(map.max_y(), Some(50));
+        assert_eq!(map.entries.len(), 10);
+        assert_eq!(map.entries[3].line, 5);
+        assert_eq!(
+            map.entries[5].directive,
+            Directive::Zone {
+                area: Rect::new(0, 10, 0, 5),
+                text: "The town square".to_string(),
+            }
+        );
+        assert_eq!(
+            map.entries[6].directive,
+            Directive::Ambience {
+                area: Rect::new(0, 10, 0, 5),
+                path: "sounds/ambience/town.mp3".to_string(),
+                volume: DEFAULT_AMBIENCE_VOLUME,
+            }
+        );
+        assert_eq!(
+            map.entries[8].directive,
+            Directive::Teleporter(Teleporter {
+                area: Rect::new(5, 5, 0, 0),
+                x: Coordinate::Range(1..=9),
+                y: Coordinate::Fixed(3),
+                map: "arena".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn reports_the_failing_line() {
+        let error = parse("map main\n\ntile 0 a 0 0 grass").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(
+            error.kind,
+            ErrorKind::InvalidNumber {
+                field: "maxx",
+                value: "a".to_string(),
+            }
+        );
+        let error = parse("tile 0 1 0 0").unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ErrorKind::ArgumentCount { found: 4, .. }
+        ));
+        let error = parse("map main\nwall 0 1 0 0").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.kind, ErrorKind::UnknownDirective("wall".to_string()));
+        let error = parse("teleporter 0 0 0 0 9...1 0 main").unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::InvalidRange { .. }));
+    }
+
+    #[test]
+    fn blank_lines_are_ignored() {
+        assert_eq!(parse("\n   \n\t\n").unwrap(), MapFile::default());
+    }
+}
@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum Error {
+    #[error("not a transport hello")]
+    NotAHello,
+    #[error("unsupported transport version {0}")]
+    Version(u8),
+    #[error("the other side's public key is weak")]
+    WeakKey,
+    #[error("packet is too short to be encrypted")]
+    TooShort,
+    #[error("packet {0} was already received")]
+    Replayed(u64),
+    #[error("packet couldn't be decrypted")]
+    Decryption,
+    #[error("packet couldn't be encrypted")]
+    Encryption,
+    #[error("the session ran out of nonces")]
+    Exhausted,
+}
@@ -0,0 +1,227 @@
+//! The encryption packets travel with between the client and the server.
+//!
+//! Right after connecting the client sends a hello holding an ephemeral X25519
+//! public key and the server answers with its own. Both sides derive a
+//! ChaCha20-Poly1305 key for each direction from the shared secret, see
+//! [`Handshake`]. Every packet after that is framed by a [`Session`] as an 8 byte
+//! little endian counter followed by the ciphertext. The counter is the nonce and
+//! has to grow with every packet, so a replayed or reordered packet is refused.
+//!
+//! The keys are not authenticated, this keeps the traffic from being read or
+//! altered on the way but doesn't stop someone able to intercept the handshake.
+mod error;
+
+pub use error::Error;
+
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+/// Starts every hello, no Fernet token or other packet starts with it.
+pub const MAGIC: &[u8; 4] = b"COAS";
+pub const VERSION: u8 = 1;
+const HELLO_LEN: usize = MAGIC.len() + 1 + 32;
+const COUNTER_LEN: usize = 8;
+const TAG_LEN: usize = 16;
+
+/// Which end of the connection a handshake is for, each sends with its own key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// Whether a packet is a hello rather than an encrypted packet.
+pub fn is_hello(packet: &[u8]) -> bool {
+    packet.starts_with(MAGIC)
+}
+
+/// One side of the key exchange, the secret is used for a single session.
+pub struct Handshake {
+    secret: EphemeralSecret,
+    public: PublicKey,
+}
+
+impl Default for Handshake {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Handshake {
+    pub fn new() -> Self {
+        let secret = EphemeralSecret::random();
+        let public = PublicKey::from(&secret);
+        Self { secret, public }
+    }
+    /// The packet that tells the other side our public key.
+    pub fn hello(&self) -> Vec<u8> {
+        let mut hello = Vec::with_capacity(HELLO_LEN);
+        hello.extend_from_slice(MAGIC);
+        hello.push(VERSION);
+        hello.extend_from_slice(self.public.as_bytes());
+        hello
+    }
+    /// Derives the session from the other side's hello.
+    pub fn finish(self, hello: &[u8], role: Role) -> Result<Session, Error> {
+        if !is_hello(hello) || hello.len() != HELLO_LEN {
+            return Err(Error::NotAHello);
+        }
+        let version = hello[MAGIC.len()];
+        if version != VERSION {
+            return Err(Error::Version(version));
+        }
+        let mut key = [0; 32];
+        key.copy_from_slice(&hello[MAGIC.len() + 1..]);
+        let theirs = PublicKey::from(key);
+        let shared = self.secret.diffie_hellman(&theirs);
+        if !shared.was_contributory() {
+            return Err(Error::WeakKey);
+        }
+        // Both public keys go into the salt in the same order on both sides.
+        let (client, server) = match role {
+            Role::Client => (self.public, theirs),
+            Role::Server => (theirs, self.public),
+        };
+        let salt = [client.as_bytes().as_slice(), server.as_bytes()].concat();
+        let hkdf = Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes());
+        let derive = |info: &[u8]| {
+            let mut key = [0; 32];
+            hkdf.expand(info, &mut key)
+                .expect("32 bytes is a valid HKDF-SHA256 output length");
+            ChaCha20Poly1305::new(&key.into())
+        };
+        let to_server = derive(b"coas client to server");
+        let to_client = derive(b"coas server to client");
+        let (send, receive) = match role {
+            Role::Client => (to_server, to_client),
+            Role::Server => (to_client, to_server),
+        };
+        Ok(Session {
+            send,
+            receive,
+            sent: 0,
+            received: None,
+        })
+    }
+}
+
+/// Encrypts and decrypts the packets of one connection.
+pub struct Session {
+    send: ChaCha20Poly1305,
+    receive: ChaCha20Poly1305,
+    /// The counter of the next packet sent.
+    sent: u64,
+    /// The counter of the last packet accepted.
+    received: Option<u64>,
+}
+
+fn nonce(counter: u64) -> Nonce {
+    let mut nonce = [0; 12];
+    nonce[..COUNTER_LEN].copy_from_slice(&counter.to_le_bytes());
+    nonce.into()
+}
+
+impl Session {
+    pub fn seal(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let counter = self.sent;
+        self.sent = counter.checked_add(1).ok_or(Error::Exhausted)?;
+        let ciphertext = self
+            .send
+            .encrypt(&nonce(counter), plaintext)
+            .map_err(|_| Error::Encryption)?;
+        let mut packet = Vec::with_capacity(COUNTER_LEN + ciphertext.len());
+        packet.extend_from_slice(&counter.to_le_bytes());
+        packet.extend_from_slice(&ciphertext);
+        Ok(packet)
+    }
+    pub fn open(&mut self, packet: &[u8]) -> Result<Vec<u8>, Error> {
+        if packet.len() < COUNTER_LEN + TAG_LEN {
+            return Err(Error::TooShort);
+        }
+        let (counter, ciphertext) = packet.split_at(COUNTER_LEN);
+        let counter = u64::from_le_bytes(counter.try_into().expect("split at 8 bytes"));
+        if self.received.is_some_and(|last| counter <= last) {
+            return Err(Error::Replayed(counter));
+        }
+        let plaintext = self
+            .receive
+            .decrypt(&nonce(counter), ciphertext)
+            .map_err(|_| Error::Decryption)?;
+        self.received = Some(counter);
+        Ok(plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connect() -> (Session, Session) {
+        let client = Handshake::new();
+        let server = Handshake::new();
+        let client_hello = client.hello();
+        let server_hello = server.hello();
+        (
+            client.finish(&server_hello, Role::Client).unwrap(),
+            server.finish(&client_hello, Role::Server).unwrap(),
+        )
+    }
+
+    #[test]
+    fn packets_reach_the_other_side() {
+        let (mut client, mut server) = connect();
+        for text in [&b"login"[..], b"", b"move"] {
+            let packet = client.seal(text).unwrap();
+            assert_eq!(packet.len(), COUNTER_LEN + text.len() + TAG_LEN);
+            assert_eq!(server.open(&packet).unwrap(), text);
+        }
+        let packet = server.seal(b"connected").unwrap();
+        assert_eq!(client.open(&packet).unwrap(), b"connected");
+    }
+
+    #[test]
+    fn replayed_packets_are_refused() {
+        let (mut client, mut server) = connect();
+        let first = client.seal(b"fire").unwrap();
+        let second = client.seal(b"fire").unwrap();
+        server.open(&second).unwrap();
+        assert_eq!(server.open(&second), Err(Error::Replayed(1)));
+        assert_eq!(server.open(&first), Err(Error::Replayed(0)));
+    }
+
+    #[test]
+    fn altered_packets_are_refused() {
+        let (mut client, mut server) = connect();
+        let mut packet = client.seal(b"chat").unwrap();
+        let last = packet.len() - 1;
+        packet[last] ^= 1;
+        assert_eq!(server.open(&packet), Err(Error::Decryption));
+        // A packet doesn't decrypt with the key of the other direction.
+        let packet = client.seal(b"chat").unwrap();
+        assert_eq!(client.open(&packet), Err(Error::Decryption));
+        assert_eq!(server.open(&[0; 4]), Err(Error::TooShort));
+    }
+
+    #[test]
+    fn bad_hellos_are_refused() {
+        let hello = Handshake::new().hello();
+        let mut old = hello.clone();
+        old[MAGIC.len()] = 0;
+        assert_eq!(
+            Handshake::new().finish(&old, Role::Server).err(),
+            Some(Error::Version(0))
+        );
+        assert_eq!(
+            Handshake::new().finish(&hello[..10], Role::Server).err(),
+            Some(Error::NotAHello)
+        );
+        let mut weak = hello;
+        weak[MAGIC.len() + 1..].fill(0);
+        assert_eq!(
+            Handshake::new().finish(&weak, Role::Server).err(),
+            Some(Error::WeakKey)
+        );
+    }
+}
@@ -87,6 +87,10 @@ impl Packet {
 
     /// Returns a reference to the bytes inside this packet.
     pub fn data(&self) -> &[u8] {
+        // ENet leaves the data pointer null for empty packets.
+        if unsafe { (*self.inner).data.is_null() } {
+            return &[];
+        }
         unsafe {
             std::slice::from_raw_parts(
                 (*self.inner).data,
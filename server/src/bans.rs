@@ -1,10 +1,85 @@
-use crate::{server::get_server, timer::Timer};
+use crate::{
+    expiry::{self, Expiry},
+    outbox::Outbox,
+    readable_time::format_time,
+    timer::Timer,
+};
+use chrono::{DateTime, Utc};
 use serde_derive::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+
+/// A ban, it applies to whoever matches the account, the hardware id or the
+/// address it holds.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Ban {
+    pub account: String,
+    /// The hardware id the account last logged in with, empty if unknown.
+    pub id: String,
+    /// Only set for IP bans, players often share addresses.
+    pub ip: Option<Ipv4Addr>,
+    pub issuer: String,
+    pub reason: String,
+    pub created: DateTime<Utc>,
+    pub expires: Expiry,
+}
+
+impl Ban {
+    pub fn new(account: &str, id: &str, issuer: &str, reason: &str) -> Self {
+        Self {
+            account: account.to_string(),
+            id: id.to_string(),
+            ip: None,
+            issuer: issuer.to_string(),
+            reason: reason.to_string(),
+            created: Utc::now(),
+            expires: Expiry::default(),
+        }
+    }
+    /// Makes the ban end after `time` milliseconds.
+    pub fn lasting(mut self, time: u64) -> Self {
+        self.expires = Expiry::after(self.created, time);
+        self
+    }
+    pub fn matches(&self, account: &str, id: &str, ip: Option<Ipv4Addr>) -> bool {
+        self.account.eq_ignore_ascii_case(account)
+            || (!id.is_empty() && self.id == id)
+            || (self.ip.is_some() && self.ip == ip)
+    }
+    /// What the banned player is told when they try to log in.
+    pub fn message(&self, now: DateTime<Utc>) -> String {
+        expiry::notice("banned", &self.issuer, &self.reason, self.expires, now)
+    }
+    /// A line of the ban list.
+    pub fn describe(&self, now: DateTime<Utc>) -> String {
+        let mut text = format!(
+            "{} by {} on {}",
+            self.account,
+            self.issuer,
+            self.created.format("%Y-%m-%d %H:%M UTC")
+        );
+        if self.ip.is_some() {
+            text += ", IP ban";
+        }
+        match self.expires.remaining(now) {
+            Some(remaining) => text += &format!(", {} remaining", format_time(remaining)),
+            None => text += ", permanent",
+        }
+        if !self.reason.is_empty() {
+            text += &format!(", Reason: {}", self.reason);
+        }
+        text
+    }
+}
+
+/// A permanent ban from before bans had reasons, see `Bans::migrate`.
 #[derive(Serialize, Deserialize, Default)]
 pub struct BannedUser {
     name: String,
     id: String,
 }
+
+/// A temporary ban from before bans had an expiry time, it lasted `time`
+/// milliseconds from when its timer started.
 #[derive(Serialize, Deserialize, Default)]
 pub struct TemporaryBan {
     name: String,
@@ -12,80 +87,58 @@ pub struct TemporaryBan {
     timer: Timer,
     id: String,
 }
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct Bans {
-    users: Vec<BannedUser>,
     #[serde(default)]
+    bans: Vec<Ban>,
+    #[serde(default, skip_serializing)]
+    users: Vec<BannedUser>,
+    #[serde(default, skip_serializing)]
     temporary: Vec<TemporaryBan>,
 }
 
 impl Bans {
-    pub fn get_user(&self, id: String) -> bool {
-        for i in &self.users {
-            if id == i.id {
-                return true;
-            }
+    /// Turns bans saved in the old format into ban records.
+    pub fn migrate(&mut self) {
+        for user in self.users.drain(..) {
+            let ban = Ban::new(&user.name, &user.id, "unknown", "");
+            self.bans.push(ban);
         }
-        false
-    }
-    pub fn add_ban(&mut self, name: String, id: String) {
-        let user = BannedUser { name, id };
-        self.users.push(user);
-    }
-    pub fn remove_ban(&mut self, name: String) -> bool {
-        for i in 0..self.users.len() {
-            if self.users[i].name == name {
-                self.users.remove(i);
-                return true;
-            }
+        for temporary in self.temporary.drain(..) {
+            let remaining = temporary.time.saturating_sub(temporary.timer.elapsed());
+            let ban = Ban::new(&temporary.name, &temporary.id, "unknown", "");
+            self.bans.push(ban.lasting(remaining));
         }
-        false
     }
-    pub fn add_temporary(&mut self, name: String, id: String, time: u64) {
-        let temp = TemporaryBan {
-            name,
-            id,
-            time,
-            timer: Timer::new(),
-        };
-        self.temporary.push(temp);
+    pub fn add(&mut self, ban: Ban) {
+        self.bans.push(ban);
     }
-    pub fn remove_temporary(&mut self, name: String) -> bool {
-        for i in 0..self.temporary.len() {
-            if self.temporary[i].name == name {
-                self.temporary.remove(i);
-                return true;
-            }
-        }
-        false
+    /// The ban keeping a player out, if any is still running.
+    pub fn find(&self, account: &str, id: &str, ip: Option<Ipv4Addr>) -> Option<&Ban> {
+        let now = Utc::now();
+        self.bans
+            .iter()
+            .find(|b| !b.expires.expired(now) && b.matches(account, id, ip))
     }
-    pub fn get_temporary(&self, id: String) -> bool {
-        for i in &self.temporary {
-            if id == i.id {
-                return true;
-            }
-        }
-        false
+    /// Lifts every ban on an account, returns how many there were.
+    pub fn remove(&mut self, account: &str) -> usize {
+        let count = self.bans.len();
+        self.bans
+            .retain(|b| !b.account.eq_ignore_ascii_case(account));
+        count - self.bans.len()
     }
-    pub fn get_temporary_remaining(&self, id: String) -> u64 {
-        for i in &self.temporary {
-            if id == i.id {
-                return i.time - i.timer.elapsed();
-            }
-        }
-        0
+    pub fn list(&self) -> &[Ban] {
+        &self.bans
     }
-    pub fn update(&mut self) -> anyhow::Result<()> {
-        for i in 0..self.temporary.len() {
-            if self.temporary[i].timer.elapsed() >= self.temporary[i].time {
-                get_server().notify(format!(
-                    "{}'s temporary ban have expired",
-                    self.temporary[i].name
-                ))?;
-                self.temporary.remove(i);
-                return Ok(());
+    pub fn update(&mut self, outbox: &mut Outbox) {
+        let now = Utc::now();
+        self.bans.retain(|ban| {
+            if ban.expires.expired(now) {
+                outbox.notify(format!("{}'s temporary ban have expired", ban.account));
+                return false;
             }
-        }
-        Ok(())
+            true
+        });
     }
 }
@@ -0,0 +1,132 @@
+use crate::config::ChatConfig;
+use regex::Regex;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blocked {
+    TooFast,
+    TooLong(usize),
+    Repeated,
+    Denied,
+}
+
+impl fmt::Display for Blocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Blocked::TooFast => write!(f, "You are sending messages too quickly"),
+            Blocked::TooLong(max) => write!(f, "Messages can't be longer than {} characters", max),
+            Blocked::Repeated => write!(f, "You just sent that"),
+            Blocked::Denied => write!(f, "Your message contains something that isn't allowed"),
+        }
+    }
+}
+
+/// The words and patterns from server.toml that messages can't contain, such
+/// as sequences that crash text to speech engines.
+pub struct DenyList {
+    words: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl DenyList {
+    pub fn new(config: &ChatConfig) -> anyhow::Result<Self> {
+        let mut patterns = vec![];
+        for pattern in &config.deny_patterns {
+            patterns.push(Regex::new(pattern)?);
+        }
+        Ok(Self {
+            words: config.deny.iter().map(|w| w.to_lowercase()).collect(),
+            patterns,
+        })
+    }
+    pub fn matches(&self, message: &str) -> bool {
+        let lower = message.to_lowercase();
+        self.words.iter().any(|w| lower.contains(w.as_str()))
+            || self.patterns.iter().any(|p| p.is_match(message))
+    }
+}
+
+/// Cuts runs of the same character down to `max` of them, 0 leaves them alone.
+pub fn collapse(message: &str, max: usize) -> String {
+    if max == 0 {
+        return message.to_string();
+    }
+    let mut result = String::with_capacity(message.len());
+    let (mut previous, mut run) = (None, 0);
+    for c in message.chars() {
+        if Some(c) == previous {
+            run += 1;
+        } else {
+            previous = Some(c);
+            run = 1;
+        }
+        if run <= max {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Checks the messages a player chats before they are broadcast, the times
+/// are game times, see `Context::now`.
+pub struct ChatFilter {
+    /// Messages the player can send right away, refilled over time.
+    tokens: f64,
+    refilled: u128,
+    last: String,
+    last_time: u128,
+    violations: usize,
+}
+
+impl ChatFilter {
+    pub fn new(config: &ChatConfig, now: u128) -> Self {
+        Self {
+            tokens: config.burst as f64,
+            refilled: now,
+            last: String::new(),
+            last_time: 0,
+            violations: 0,
+        }
+    }
+    /// Returns the message as it should be sent, or why it can't be.
+    pub fn check(
+        &mut self,
+        config: &ChatConfig,
+        deny: &DenyList,
+        now: u128,
+        message: &str,
+    ) -> Result<String, Blocked> {
+        let refill = (now - self.refilled) as f64 * config.per_minute as f64 / 60000.0;
+        self.tokens = (self.tokens + refill).min(config.burst as f64);
+        self.refilled = now;
+        if self.tokens < 1.0 {
+            return Err(Blocked::TooFast);
+        }
+        self.tokens -= 1.0;
+        if deny.matches(message) {
+            return Err(Blocked::Denied);
+        }
+        let message = collapse(message, config.max_repeated_characters);
+        if config.max_length > 0 && message.chars().count() > config.max_length {
+            return Err(Blocked::TooLong(config.max_length));
+        }
+        let normalized = message.trim().to_lowercase();
+        let window = config.duplicate_time().unwrap_or(0) as u128;
+        if !normalized.is_empty() && normalized == self.last && now - self.last_time < window {
+            return Err(Blocked::Repeated);
+        }
+        self.last = normalized;
+        self.last_time = now;
+        Ok(message)
+    }
+    /// Counts a blocked message, returns true once there were enough that the
+    /// player should be muted.
+    pub fn violation(&mut self, config: &ChatConfig) -> bool {
+        self.violations += 1;
+        if config.violations > 0 && self.violations >= config.violations {
+            self.violations = 0;
+            return true;
+        }
+        false
+    }
+}
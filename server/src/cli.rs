@@ -0,0 +1,138 @@
+use crate::{config::ServerConfig, keys::Keys, password, server::Server, storage};
+use clap::{Parser, Subcommand};
+use std::{net::Ipv4Addr, path::PathBuf};
+
+/// Contacts on all sides game server.
+///
+/// Settings are read from the config file, the options here override them.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    #[arg(long, env = "COAS_CONFIG", default_value = "server.toml")]
+    pub config: PathBuf,
+    #[arg(long, env = "COAS_BIND")]
+    pub bind: Option<Ipv4Addr>,
+    #[arg(long, env = "COAS_PORT")]
+    pub port: Option<u16>,
+    #[arg(long, env = "COAS_MAX_PEERS")]
+    pub max_peers: Option<usize>,
+    /// Bytes per second, 0 is unlimited.
+    #[arg(long, env = "COAS_INCOMING_BANDWIDTH")]
+    pub incoming_bandwidth: Option<u32>,
+    /// Bytes per second, 0 is unlimited.
+    #[arg(long, env = "COAS_OUTGOING_BANDWIDTH")]
+    pub outgoing_bandwidth: Option<u32>,
+    /// Holds the players directory, server.dat and note.txt.
+    #[arg(long, env = "COAS_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    #[arg(long, env = "COAS_MAPS_DIR")]
+    pub maps_dir: Option<PathBuf>,
+    /// Map ambience files are checked against it.
+    #[arg(long, env = "COAS_CLIENT_DIR")]
+    pub client_dir: Option<PathBuf>,
+    /// `files` or `sqlite:<path>`.
+    #[arg(long, env = "COAS_STORAGE")]
+    pub storage: Option<String>,
+    #[arg(long, env = "COAS_KEYS")]
+    pub keys: Option<PathBuf>,
+    #[arg(long, env = "COAS_MIN_CLIENT_VERSION")]
+    pub min_client_version: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Hashes the passwords of accounts that still have plaintext ones.
+    MigratePasswords,
+    /// Copies every account from one storage to another, e.g. `files sqlite:coas.db`.
+    ImportStorage { from: String, to: String },
+    /// Creates a keys file with new random keys, for new servers.
+    GenerateKeys,
+    /// Creates the keys file from the keys in another one, for servers with
+    /// player data or clients from before keys.toml.
+    ImportKeys { from: PathBuf },
+}
+
+impl Cli {
+    /// Loads the config file and applies the options given on the command line.
+    pub fn load_config(&self) -> anyhow::Result<ServerConfig> {
+        let mut config = ServerConfig::load(&self.config)?;
+        let network = &mut config.network;
+        if let Some(bind) = self.bind {
+            network.bind = bind;
+        }
+        if let Some(port) = self.port {
+            network.port = port;
+        }
+        if let Some(max_peers) = self.max_peers {
+            network.max_peers = max_peers;
+        }
+        if let Some(bandwidth) = self.incoming_bandwidth {
+            network.incoming_bandwidth = bandwidth;
+        }
+        if let Some(bandwidth) = self.outgoing_bandwidth {
+            network.outgoing_bandwidth = bandwidth;
+        }
+        let paths = &mut config.paths;
+        if let Some(data) = &self.data_dir {
+            paths.data = data.clone();
+        }
+        if let Some(maps) = &self.maps_dir {
+            paths.maps = maps.clone();
+        }
+        if let Some(client) = &self.client_dir {
+            paths.client = Some(client.clone());
+        }
+        if let Some(storage) = &self.storage {
+            paths.storage = storage.clone();
+        }
+        if let Some(keys) = &self.keys {
+            paths.keys = keys.clone();
+        }
+        if let Some(version) = &self.min_client_version {
+            config.client.minimum_version = version.clone();
+        }
+        config.validate()?;
+        Ok(config)
+    }
+    /// Runs the subcommand if one was given, otherwise the server until it is stopped.
+    pub fn run(&self) -> anyhow::Result<()> {
+        let config = self.load_config()?;
+        match &self.command {
+            Some(Command::GenerateKeys) => {
+                Keys::generate().save(&config.paths.keys)?;
+                println!("Created {}", config.paths.keys.display());
+                return Ok(());
+            }
+            Some(Command::ImportKeys { from }) => {
+                Keys::load(from)?.save(&config.paths.keys)?;
+                println!(
+                    "Created {} with the keys from {}",
+                    config.paths.keys.display(),
+                    from.display()
+                );
+                return Ok(());
+            }
+            Some(Command::MigratePasswords) => {
+                let keys = Keys::load(&config.paths.keys)?;
+                let storage = storage::open(&config.paths.storage, &config.paths.data, &keys)?;
+                let count = password::migrate(storage.as_ref())?;
+                println!("Hashed {} plaintext passwords", count);
+                return Ok(());
+            }
+            Some(Command::ImportStorage { from, to }) => {
+                let keys = Keys::load(&config.paths.keys)?;
+                let source = storage::open(from, &config.paths.data, &keys)?;
+                let target = storage::open(to, &config.paths.data, &keys)?;
+                let count = storage::import(source.as_ref(), target.as_ref())?;
+                println!("Imported {} accounts from {} to {}", count, from, to);
+                return Ok(());
+            }
+            None => (),
+        }
+        let mut server = Server::new(config)?;
+        server.load()?;
+        server.run()
+    }
+}
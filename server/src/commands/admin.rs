@@ -0,0 +1,840 @@
+use super::{optional, required, Args, Call, Command, Kind};
+use crate::{
+    bans::Ban,
+    config::PenaltyAction,
+    maps,
+    mutes::Mute,
+    player::{self, Player, PlayerData, Players},
+    readable_time::format_time,
+    server::{packets, packets::packet::Data},
+    warnings::{self, Warning},
+};
+use chrono::Utc;
+use enet::Peer;
+use std::fs;
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "grant",
+        aliases: &[],
+        permission: Some("roles"),
+        args: &[
+            required("player", Kind::Account),
+            required("role", Kind::Role),
+        ],
+        description: "Gives a player a role",
+        handler: grant,
+    },
+    Command {
+        name: "revoke",
+        aliases: &[],
+        permission: Some("roles"),
+        args: &[
+            required("player", Kind::Account),
+            required("role", Kind::Role),
+        ],
+        description: "Takes a role away from a player",
+        handler: revoke,
+    },
+    Command {
+        name: "roles",
+        aliases: &[],
+        permission: None,
+        args: &[optional("player", Kind::Account)],
+        description: "Lists your roles, or another player's if you can grant roles",
+        handler: roles,
+    },
+    Command {
+        name: "at",
+        aliases: &[],
+        permission: Some("admin.chat"),
+        args: &[required("message", Kind::Rest)],
+        description: "Talks in the admin chat",
+        handler: at,
+    },
+    Command {
+        name: "notify",
+        aliases: &[],
+        permission: Some("notify"),
+        args: &[required("message", Kind::Rest)],
+        description: "Sends a notification to everyone",
+        handler: notify,
+    },
+    Command {
+        name: "note",
+        aliases: &[],
+        permission: Some("note"),
+        args: &[required("note", Kind::Rest)],
+        description: "Changes the server note",
+        handler: note,
+    },
+    Command {
+        name: "mute",
+        aliases: &[],
+        permission: Some("mute"),
+        args: &[
+            required("player", Kind::Account),
+            required("time", Kind::Duration),
+            optional("reason", Kind::Rest),
+        ],
+        description: "Stops a player from chatting for a while, a time without a unit is minutes",
+        handler: mute,
+    },
+    Command {
+        name: "silentmute",
+        aliases: &[],
+        permission: Some("mute"),
+        args: &[
+            required("player", Kind::Account),
+            required("time", Kind::Duration),
+            optional("reason", Kind::Rest),
+        ],
+        description: "Mutes a player for a while, only telling the admins",
+        handler: silentmute,
+    },
+    Command {
+        name: "unmute",
+        aliases: &[],
+        permission: Some("mute"),
+        args: &[required("player", Kind::Account)],
+        description: "Lets a muted player chat again",
+        handler: unmute,
+    },
+    Command {
+        name: "warn",
+        aliases: &[],
+        permission: Some("warn"),
+        args: &[
+            required("player", Kind::Account),
+            required("reason", Kind::Rest),
+        ],
+        description: "Warns a player, enough warnings mute or ban them",
+        handler: warn,
+    },
+    Command {
+        name: "warnings",
+        aliases: &[],
+        permission: Some("warn"),
+        args: &[required("player", Kind::Account)],
+        description: "Lists a player's warnings",
+        handler: list_warnings,
+    },
+    Command {
+        name: "kick",
+        aliases: &[],
+        permission: Some("kick"),
+        args: &[required("player", Kind::Player)],
+        description: "Disconnects a player",
+        handler: kick,
+    },
+    Command {
+        name: "ban",
+        aliases: &[],
+        permission: Some("ban"),
+        args: &[
+            required("player", Kind::Account),
+            optional("reason", Kind::Rest),
+        ],
+        description: "Bans a player for good",
+        handler: ban,
+    },
+    Command {
+        name: "ipban",
+        aliases: &[],
+        permission: Some("ban"),
+        args: &[
+            required("player", Kind::Account),
+            optional("reason", Kind::Rest),
+        ],
+        description: "Bans a player and the address they last played from for good",
+        handler: ipban,
+    },
+    Command {
+        name: "tempban",
+        aliases: &["timed_ban"],
+        permission: Some("ban"),
+        args: &[
+            required("player", Kind::Account),
+            required("time", Kind::Duration),
+            optional("reason", Kind::Rest),
+        ],
+        description: "Bans a player for a while, a time without a unit is minutes",
+        handler: tempban,
+    },
+    Command {
+        name: "unban",
+        aliases: &[],
+        permission: Some("ban"),
+        args: &[required("player", Kind::Account)],
+        description: "Lifts a player's bans",
+        handler: unban,
+    },
+    Command {
+        name: "banlist",
+        aliases: &[],
+        permission: Some("ban"),
+        args: &[],
+        description: "Lists the bans",
+        handler: banlist,
+    },
+    Command {
+        name: "give",
+        aliases: &[],
+        permission: Some("give"),
+        args: &[
+            required("player", Kind::Player),
+            required("item", Kind::Item),
+            required("amount", Kind::Integer),
+        ],
+        description: "Gives a player some items",
+        handler: give,
+    },
+    Command {
+        name: "giveall",
+        aliases: &[],
+        permission: Some("give"),
+        args: &[
+            required("item", Kind::Item),
+            required("amount", Kind::Integer),
+        ],
+        description: "Gives everyone some items",
+        handler: giveall,
+    },
+    Command {
+        name: "move",
+        aliases: &[],
+        permission: Some("move"),
+        args: &[
+            required("player", Kind::Player),
+            required("x", Kind::Integer),
+            required("y", Kind::Integer),
+            optional("map", Kind::Map),
+        ],
+        description: "Moves a player, to another map if one is given",
+        handler: move_player,
+    },
+    Command {
+        name: "rawmap",
+        aliases: &[],
+        permission: Some("map.edit"),
+        args: &[],
+        description: "Copies the source of the map you are on",
+        handler: rawmap,
+    },
+    Command {
+        name: "rawdata",
+        aliases: &[],
+        permission: Some("map.edit"),
+        args: &[required("map", Kind::Rest)],
+        description: "Replaces the map you are on with new source",
+        handler: rawdata,
+    },
+    Command {
+        name: "newmap",
+        aliases: &[],
+        permission: Some("map.edit"),
+        args: &[
+            required("name", Kind::MapName),
+            required("maxx", Kind::Integer),
+            required("maxy", Kind::Integer),
+            required("tile", Kind::Word),
+        ],
+        description: "Creates a map with a floor and takes you there",
+        handler: newmap,
+    },
+    Command {
+        name: "remmap",
+        aliases: &[],
+        permission: Some("map.edit"),
+        args: &[optional("map", Kind::Map)],
+        description: "Deletes a map, the one you are on if none is given",
+        handler: remmap,
+    },
+    Command {
+        name: "save",
+        aliases: &[],
+        permission: Some("restart"),
+        args: &[],
+        description: "Saves everything and restarts the server",
+        handler: save,
+    },
+];
+
+/// Looks up the online player an argument names, the dispatcher has already
+/// checked they are online.
+fn online<'a>(
+    players: &'a mut Players,
+    args: &Args,
+    index: usize,
+) -> anyhow::Result<&'a mut Player> {
+    let name = args.text(index)?;
+    match player::find(players, name) {
+        Some(player) => Ok(player),
+        None => anyhow::bail!("{} is not online", name),
+    }
+}
+
+/// Sends a player away and tells everyone they left.
+fn disconnect(call: &mut Call, peer: Peer) -> anyhow::Result<()> {
+    let handle = match call.players.remove(&peer) {
+        Some(handle) => handle,
+        None => return Ok(()),
+    };
+    call.ctx
+        .outbox
+        .send(handle.addr, Data::Close(packets::Close::default()));
+    let off = packets::Offline {
+        who: handle.name.clone(),
+    };
+    call.ctx.outbox.broadcast(Data::Offline(off));
+    handle.save(call.ctx)
+}
+
+/// Changes a player's data whether they are online or not, offline players
+/// are saved if the change returned true.
+fn edit_account<F>(call: &mut Call, name: &str, edit: F) -> anyhow::Result<bool>
+where
+    F: FnOnce(&mut PlayerData) -> bool,
+{
+    if let Some(handle) = player::find(call.players, name) {
+        return Ok(edit(&mut handle.data));
+    }
+    match call.load_offline(name)? {
+        Some(mut target) => {
+            let changed = edit(&mut target.data);
+            if changed {
+                target.save(call.ctx)?;
+            }
+            Ok(changed)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Changes the roles granted to a player whether they are online or not,
+/// returns what the change returned.
+fn edit_roles<F>(call: &mut Call, name: &str, edit: F) -> anyhow::Result<bool>
+where
+    F: FnOnce(&mut Vec<String>) -> bool,
+{
+    edit_account(call, name, |data| edit(&mut data.roles))
+}
+
+/// Players can only hand out roles that don't give more than they have themselves.
+fn can_grant(call: &Call, role: &str) -> bool {
+    call.ctx
+        .config
+        .role(role)
+        .is_some_and(|r| r.permissions.iter().all(|p| call.has_permission(p)))
+}
+
+fn grant(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let (name, role) = (args.text(0)?, args.text(1)?);
+    if !can_grant(call, role) {
+        return call.say(format!(
+            "Error: The {} role has permissions you don't have",
+            role
+        ));
+    }
+    let added = edit_roles(call, name, |roles| {
+        if roles.iter().any(|r| r == role) {
+            return false;
+        }
+        roles.push(role.to_string());
+        true
+    })?;
+    if !added {
+        return call.say(format!("{} already has the {} role", name, role));
+    }
+    if let Some(handle) = player::find(call.players, name) {
+        handle.say(call.ctx, format!("You now have the {} role", role))?;
+    }
+    call.ctx.outbox.admin_tell(format!(
+        "{} has given {} the {} role",
+        call.name, name, role
+    ));
+    Ok(())
+}
+
+fn revoke(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let (name, role) = (args.text(0)?, args.text(1)?);
+    if !can_grant(call, role) {
+        return call.say(format!(
+            "Error: The {} role has permissions you don't have",
+            role
+        ));
+    }
+    let removed = edit_roles(call, name, |roles| {
+        let count = roles.len();
+        roles.retain(|r| r != role);
+        roles.len() != count
+    })?;
+    if !removed {
+        return call.say(format!(
+            "{} wasn't granted the {} role, Roles from server.toml can only be changed there",
+            name, role
+        ));
+    }
+    if let Some(handle) = player::find(call.players, name) {
+        handle.say(call.ctx, format!("You no longer have the {} role", role))?;
+    }
+    call.ctx.outbox.admin_tell(format!(
+        "{} has taken the {} role from {}",
+        call.name, role, name
+    ));
+    Ok(())
+}
+
+fn roles(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let name = match args.len() {
+        1 => args.text(0)?.to_string(),
+        _ => call.name.clone(),
+    };
+    let granted = if name.to_lowercase() == call.name.to_lowercase() {
+        call.player()?.data.roles.clone()
+    } else if !call.has_permission("roles") {
+        return call.say(String::from("You can only see your own roles"));
+    } else if let Some(handle) = player::find(call.players, &name) {
+        handle.data.roles.clone()
+    } else {
+        match call.load_offline(&name)? {
+            Some(target) => target.data.roles,
+            None => vec![],
+        }
+    };
+    let held = call
+        .ctx
+        .config
+        .roles_of(&name, &granted)
+        .cloned()
+        .collect::<Vec<String>>();
+    if held.is_empty() {
+        return call.say(format!("{} has no roles", name));
+    }
+    call.say(format!("{} has the roles {}", name, held.join(", ")))
+}
+
+fn at(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    call.ctx
+        .outbox
+        .admin_tell(format!("Admin chat from {}: {}", call.name, args.text(0)?));
+    Ok(())
+}
+
+fn notify(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    call.ctx.outbox.notify(args.text(0)?.to_string());
+    call.ctx.outbox.admin_tell(format!(
+        "{} Has just sent a notification to the server!",
+        call.name
+    ));
+    Ok(())
+}
+
+fn note(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let note = args.text(0)?;
+    fs::write(call.ctx.config.note_path(), note)?;
+    call.ctx.outbox.notify(format!(
+        "{} Have just changed the server note to {}",
+        call.name, note
+    ));
+    Ok(())
+}
+
+fn kick(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let handle = online(call.players, args, 0)?;
+    let (name, peer) = (handle.name.clone(), handle.addr);
+    call.ctx
+        .outbox
+        .notify(format!("{} have been kicked by {}", name, call.name));
+    disconnect(call, peer)
+}
+
+/// The optional reason argument at `index`.
+fn reason(args: &Args, index: usize) -> anyhow::Result<&str> {
+    match args.len() > index {
+        true => args.text(index),
+        false => Ok(""),
+    }
+}
+
+/// Bans a player whether they are online or not, online players are disconnected.
+fn add_ban(
+    call: &mut Call,
+    name: &str,
+    time: Option<u64>,
+    ip: bool,
+    reason: &str,
+) -> anyhow::Result<()> {
+    if call.ctx.data.bans.find(name, "", None).is_some() {
+        return call.say(format!("{} is already banned, Use /unban first", name));
+    }
+    let (account, id, address, peer) = match player::find(call.players, name) {
+        Some(handle) => (
+            handle.name.clone(),
+            handle.data.id.clone(),
+            handle.data.ip,
+            Some(handle.addr),
+        ),
+        None => match call.load_offline(name)? {
+            Some(target) => (target.name, target.data.id, target.data.ip, None),
+            None => return Ok(()),
+        },
+    };
+    let mut ban = Ban::new(&account, &id, &call.name, reason);
+    if let Some(time) = time {
+        ban = ban.lasting(time);
+    }
+    if ip {
+        match address {
+            Some(address) => ban.ip = Some(address),
+            None => return call.say(format!("{} has no known address", account)),
+        }
+    }
+    let mut text = match time {
+        Some(time) => format!(
+            "{} Have been temporarily banned by {} For {}",
+            account,
+            call.name,
+            format_time(time.into())
+        ),
+        None => format!("{} Have been banned by {}", account, call.name),
+    };
+    if !reason.is_empty() {
+        text += &format!(", Reason: {}", reason);
+    }
+    call.ctx.data.bans.add(ban);
+    call.ctx.save()?;
+    call.ctx.outbox.notify(text);
+    match peer {
+        Some(peer) => disconnect(call, peer),
+        None => Ok(()),
+    }
+}
+
+fn warn(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let (name, reason) = (args.text(0)?, args.text(1)?);
+    let warning = Warning::new(&call.name, reason);
+    let mut count = 0;
+    edit_account(call, name, |data| {
+        data.warnings.push(warning);
+        count = data.warnings.len();
+        true
+    })?;
+    if let Some(handle) = player::find(call.players, name) {
+        let text = format!(
+            "You have been warned by {}, Reason: {}, You have {} warnings",
+            call.name, reason, count
+        );
+        call.ctx.outbox.say(handle.addr, text);
+    }
+    call.ctx.outbox.admin_tell(format!(
+        "{} warned {}, Reason: {}, They have {} warnings",
+        call.name, name, reason, count
+    ));
+    penalize(call, name, count)
+}
+
+/// Applies the step of the warning ladder a player reached, if any.
+fn penalize(call: &mut Call, name: &str, warnings: usize) -> anyhow::Result<()> {
+    let (action, time) = match call.ctx.config.penalty(warnings) {
+        Some(penalty) => (penalty.action, penalty.time()),
+        None => return Ok(()),
+    };
+    let reason = format!("Reached {} warnings", warnings);
+    match action {
+        PenaltyAction::Mute => add_mute(call, name, time, false, &reason),
+        PenaltyAction::Kick => match player::find(call.players, name) {
+            Some(handle) => {
+                let (name, peer) = (handle.name.clone(), handle.addr);
+                call.ctx.outbox.notify(format!(
+                    "{} have been kicked by {}, Reason: {}",
+                    name, call.name, reason
+                ));
+                disconnect(call, peer)
+            }
+            None => Ok(()),
+        },
+        PenaltyAction::Ban => add_ban(call, name, time, false, &reason),
+    }
+}
+
+/// Stops a player from chatting for `time` milliseconds, or until they are
+/// unmuted, replacing any mute they had. Silent mutes are only told to admins.
+fn add_mute(
+    call: &mut Call,
+    name: &str,
+    time: Option<u64>,
+    silent: bool,
+    reason: &str,
+) -> anyhow::Result<()> {
+    let mut mute = Mute::new(&call.name, reason).silent(silent);
+    if let Some(time) = time {
+        mute = mute.lasting(time);
+    }
+    edit_account(call, name, |data| {
+        data.mute = Some(mute);
+        true
+    })?;
+    let mut text = match time {
+        Some(time) => format!(
+            "{} Have been muted by {} For {}",
+            name,
+            call.name,
+            format_time(time.into())
+        ),
+        None => format!("{} Have been muted by {}", name, call.name),
+    };
+    if !reason.is_empty() {
+        text += &format!(", Reason: {}", reason);
+    }
+    match silent {
+        true => call.ctx.outbox.admin_tell(format!("{}, Silently", text)),
+        false => call.ctx.outbox.notify(text),
+    }
+    Ok(())
+}
+
+fn mute(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let time = args.duration(1)?;
+    add_mute(call, args.text(0)?, Some(time), false, reason(args, 2)?)
+}
+
+fn silentmute(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let time = args.duration(1)?;
+    add_mute(call, args.text(0)?, Some(time), true, reason(args, 2)?)
+}
+
+fn unmute(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let name = args.text(0)?;
+    let mut lifted = None;
+    edit_account(call, name, |data| {
+        lifted = data.mute.take();
+        lifted.is_some()
+    })?;
+    let mute = match lifted {
+        Some(mute) => mute,
+        None => return call.say(format!("{} is not muted", name)),
+    };
+    if let Some(handle) = player::find(call.players, name) {
+        let text = String::from("You have been unmuted, You can chat again");
+        call.ctx.outbox.say(handle.addr, text);
+    }
+    let text = format!("{} Have been unmuted by {}", name, call.name);
+    match mute.silent {
+        true => call.ctx.outbox.admin_tell(text),
+        false => call.ctx.outbox.notify(text),
+    }
+    Ok(())
+}
+
+fn list_warnings(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let name = args.text(0)?;
+    let list = match player::find(call.players, name) {
+        Some(handle) => warnings::list(&handle.data.warnings),
+        None => match call.load_offline(name)? {
+            Some(target) => warnings::list(&target.data.warnings),
+            None => return Ok(()),
+        },
+    };
+    if list.is_empty() {
+        return call.say(format!("{} has no warnings", name));
+    }
+    call.buffer(list)
+}
+
+fn ban(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    add_ban(call, args.text(0)?, None, false, reason(args, 1)?)
+}
+
+fn ipban(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    add_ban(call, args.text(0)?, None, true, reason(args, 1)?)
+}
+
+fn tempban(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let time = args.duration(1)?;
+    add_ban(call, args.text(0)?, Some(time), false, reason(args, 2)?)
+}
+
+fn unban(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let name = args.text(0)?;
+    if call.ctx.data.bans.remove(name) == 0 {
+        return call.say(format!("{} is not banned", name));
+    }
+    call.ctx.save()?;
+    call.ctx
+        .outbox
+        .notify(format!("{} Have been unbanned by {}", name, call.name));
+    Ok(())
+}
+
+fn banlist(call: &mut Call, _args: &Args) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let bans = call.ctx.data.bans.list();
+    if bans.is_empty() {
+        return call.say(String::from("Nobody is banned"));
+    }
+    let lines = bans
+        .iter()
+        .map(|b| b.describe(now))
+        .collect::<Vec<String>>();
+    call.buffer(lines.join("\n"))
+}
+
+fn give(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let item = args.text(1)?;
+    let amount = args.integer(2)?;
+    let handle = online(call.players, args, 0)?;
+    handle.give(call.ctx, item, amount)?;
+    let text = format!(
+        "{} has just given {} {} {}s",
+        call.name, handle.name, amount, item
+    );
+    call.ctx.outbox.admin_tell(text);
+    Ok(())
+}
+
+fn giveall(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let item = args.text(0)?;
+    let amount = args.integer(1)?;
+    for i in call.players.values_mut() {
+        i.give(call.ctx, item, amount)?;
+    }
+    call.ctx.outbox.admin_tell(format!(
+        "{} has just given everyone {} {}s",
+        call.name, amount, item
+    ));
+    Ok(())
+}
+
+fn move_player(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let (x, y) = (args.integer(1)?, args.integer(2)?);
+    let handle = online(call.players, args, 0)?;
+    let map = match args.len() {
+        4 => args.text(3)?.to_string(),
+        _ => handle.data.map.clone(),
+    };
+    if let Err(e) = handle.change_map(call.ctx, x, y, map) {
+        call.say(format!(
+            "Error: Couldn't change the player's location, Reason: {}",
+            e
+        ))?;
+    }
+    Ok(())
+}
+
+fn rawmap(call: &mut Call, _args: &Args) -> anyhow::Result<()> {
+    let map = call.player()?.data.map.clone();
+    let buf = packets::Buffer {
+        text: fs::read_to_string(call.ctx.config.map_path(&map))?,
+        name: String::new(),
+        sound: String::new(),
+    };
+    call.ctx.outbox.send(call.peer, Data::Buffer(buf));
+    Ok(())
+}
+
+fn rawdata(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let data = args.text(0)?.to_string();
+    let name = call.player()?.data.map.clone();
+    if !check_map(call, &name, &data)? {
+        return Ok(());
+    }
+    let map = maps::grid::Grid::from_file(&coas_map::parse(&data)?)?;
+    if let Err(e) = map.check_items(&call.ctx.item_registry) {
+        return call.say(format!("Error: {}", e));
+    }
+    fs::write(call.ctx.config.map_path(&name), &data)?;
+    call.ctx.maps.insert(name.clone(), map);
+    let parse = packets::ParseMap { data };
+    call.ctx.outbox.broadcast_map(Data::ParseMap(parse), &name);
+    call.ctx.outbox.broadcast_map(
+        Data::Buffer(packets::Buffer {
+            text: String::from("Map updated!"),
+            name: String::new(),
+            sound: String::new(),
+        }),
+        &name,
+    );
+    Ok(())
+}
+
+/// Lints a map the caller is about to save, see `Player::check_map`.
+fn check_map(call: &mut Call, name: &str, data: &str) -> anyhow::Result<bool> {
+    match call.players.get(&call.peer) {
+        Some(player) => player.check_map(call.ctx, name, data),
+        None => Ok(false),
+    }
+}
+
+fn newmap(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let name = args.text(0)?;
+    let (max_x, max_y) = (args.integer(1)?, args.integer(2)?);
+    if call.ctx.maps.contains_key(name) {
+        return call.say("That map already exists!".to_string());
+    }
+    let text = format!(
+        "map {}
+                maxx {}
+                maxy {}
+                tile 0 {} 0 0 {}",
+        name,
+        max_x,
+        max_y,
+        max_x,
+        args.text(3)?
+    );
+    if !check_map(call, name, &text)? {
+        return Ok(());
+    }
+    let path = call.ctx.config.map_path(name);
+    if let Err(e) = fs::write(&path, text) {
+        return call.say(format!("Error: Unable to create the map, Reason: {}", e));
+    }
+    call.ctx
+        .maps
+        .insert(name.to_string(), maps::parse_map(&path)?);
+    if let Some(player) = call.players.get_mut(&call.peer) {
+        player.change_map(call.ctx, 0, 0, name.to_string())?;
+    }
+    call.ctx
+        .outbox
+        .admin_tell(format!("{} Has created a new map: {}", call.name, name));
+    Ok(())
+}
+
+fn remmap(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let map = match args.len() {
+        1 => args.text(0)?.to_string(),
+        _ => call.player()?.data.map.clone(),
+    };
+    if map == "main" || map == "safe_zone" {
+        return call.say("Error: This map cannot be deleted".to_string());
+    }
+    if let Err(e) = fs::remove_file(call.ctx.config.map_path(&map)) {
+        return call.say(format!("Error: Unable to delete the map, Reason: {}", e));
+    }
+    call.ctx.maps.remove(&map);
+    call.say("Success".to_string())?;
+    call.ctx
+        .outbox
+        .admin_tell(format!("{} Have deleted the map {}", call.name, map));
+    for i in call.players.values_mut() {
+        if i.data.map == map {
+            i.change_map(call.ctx, 0, 0, "main".to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn save(call: &mut Call, _args: &Args) -> anyhow::Result<()> {
+    for i in call.players.values() {
+        i.save(call.ctx)?;
+        call.ctx
+            .outbox
+            .send(i.addr, Data::Close(packets::Close::default()));
+    }
+    println!("Server have been restarted by {}", call.name);
+    call.ctx.save()?;
+    call.ctx.stopping = true;
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use super::{optional, required, Args, Call, Command, Kind, COMMANDS as REGISTRY};
+use crate::{
+    server::{packets, packets::packet::Data, RULES},
+    warnings,
+};
+use chrono::Utc;
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        aliases: &["commands"],
+        permission: None,
+        args: &[optional("command", Kind::Word)],
+        description: "Lists the commands you can use, or explains one of them",
+        handler: help,
+    },
+    Command {
+        name: "rules",
+        aliases: &[],
+        permission: None,
+        args: &[],
+        description: "Shows the game rules",
+        handler: rules,
+    },
+    Command {
+        name: "agree",
+        aliases: &[],
+        permission: None,
+        args: &[],
+        description: "Agrees to the game rules so you can chat",
+        handler: agree,
+    },
+    Command {
+        name: "hit_ping",
+        aliases: &[],
+        permission: None,
+        args: &[],
+        description: "Turns the sound when you hit someone on or off",
+        handler: hit_ping,
+    },
+    Command {
+        name: "me",
+        aliases: &[],
+        permission: None,
+        args: &[required("action", Kind::Rest)],
+        description: "Describes something you do",
+        handler: me,
+    },
+    Command {
+        name: "mywarnings",
+        aliases: &[],
+        permission: None,
+        args: &[],
+        description: "Lists the warnings you were given",
+        handler: mywarnings,
+    },
+    Command {
+        name: "admintell",
+        aliases: &[],
+        permission: None,
+        args: &[required("message", Kind::Rest)],
+        description: "Sends a message to the admins",
+        handler: admintell,
+    },
+];
+
+fn help(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    if args.len() == 1 {
+        let name = args.text(0)?.trim_start_matches('/');
+        return match REGISTRY.get(name) {
+            Some(command) if command.allows(call) => {
+                let mut text = format!("{}: {}", command.usage(), command.description);
+                if !command.aliases.is_empty() {
+                    text += &format!(", Also /{}", command.aliases.join(", /"));
+                }
+                call.say(text)
+            }
+            _ => call.say(format!("Unknown command /{}", name)),
+        };
+    }
+    let lines = REGISTRY
+        .available(call)
+        .map(|c| format!("{}: {}", c.usage(), c.description))
+        .collect::<Vec<String>>();
+    call.buffer(lines.join("\n"))
+}
+
+fn rules(call: &mut Call, _args: &Args) -> anyhow::Result<()> {
+    call.buffer(RULES.to_string())
+}
+
+fn agree(call: &mut Call, _args: &Args) -> anyhow::Result<()> {
+    let player = call.player()?;
+    if player.data.agreed_to_rules {
+        return call.say(String::from("You have already agreed to the rules!"));
+    }
+    player.data.agreed_to_rules = true;
+    call.say(String::from(
+        "Success, You have agreed to the rules, Welcome to the game.",
+    ))
+}
+
+fn hit_ping(call: &mut Call, _args: &Args) -> anyhow::Result<()> {
+    let player = call.player()?;
+    player.data.hit_ping = !player.data.hit_ping;
+    if player.data.hit_ping {
+        call.say("Hitting ping turned on!".to_string())
+    } else {
+        call.say("Hitting ping turned off!".to_string())
+    }
+}
+
+fn me(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let buf = packets::Buffer {
+        text: format!("{} {}", call.name, args.text(0)?),
+        name: String::from("chat"),
+        sound: String::from("notifications/chat.mp3"),
+    };
+    call.ctx.outbox.send(call.peer, Data::Buffer(buf));
+    Ok(())
+}
+
+fn mywarnings(call: &mut Call, _args: &Args) -> anyhow::Result<()> {
+    let list = warnings::list(&call.player()?.data.warnings);
+    if list.is_empty() {
+        return call.say(String::from("You have no warnings"));
+    }
+    call.buffer(list)
+}
+
+fn admintell(call: &mut Call, args: &Args) -> anyhow::Result<()> {
+    let Some(player) = call.players.get_mut(&call.peer) else {
+        return Ok(());
+    };
+    if let Some(mute) = &player.data.mute {
+        call.ctx.outbox.say(call.peer, mute.message(Utc::now()));
+        return Ok(());
+    }
+    // Admins hear these the same way as chat, so they go through the same filter.
+    let Some(message) = player.filter_chat(call.ctx, args.text(0)?)? else {
+        return Ok(());
+    };
+    call.ctx
+        .outbox
+        .admin_tell(format!("Admin tell from {}: {}", call.name, message));
+    Ok(())
+}
@@ -0,0 +1,321 @@
+//! Chat commands, the messages starting with a slash.
+//!
+//! Every command declares who may run it and what arguments it takes, the
+//! dispatcher checks both before the handler runs so handlers only deal with
+//! arguments that are already valid.
+mod admin;
+mod general;
+
+use crate::{
+    player::{self, Player, Players},
+    readable_time::parse_time,
+    server::{packets, packets::packet::Data, Context},
+};
+use enet::Peer;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+pub static COMMANDS: Lazy<CommandRegistry> = Lazy::new(CommandRegistry::new);
+
+type Handler = fn(&mut Call, &Args) -> anyhow::Result<()>;
+
+/// A command being run, who ran it and everything its handler can act on.
+pub struct Call<'a> {
+    pub peer: Peer,
+    /// The name of the player who ran the command.
+    pub name: String,
+    pub players: &'a mut Players,
+    pub ctx: &'a mut Context,
+}
+
+impl Call<'_> {
+    /// The player who ran the command, they can be gone if a handler kicked them.
+    pub fn player(&mut self) -> anyhow::Result<&mut Player> {
+        match self.players.get_mut(&self.peer) {
+            Some(player) => Ok(player),
+            None => anyhow::bail!("{} is no longer online", self.name),
+        }
+    }
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.players
+            .get(&self.peer)
+            .is_some_and(|p| p.has_permission(&self.ctx.config, permission))
+    }
+    pub fn say(&mut self, text: String) -> anyhow::Result<()> {
+        self.ctx.outbox.say(self.peer, text);
+        Ok(())
+    }
+    /// Sends text too long for a chat message to the caller's buffer.
+    pub fn buffer(&mut self, text: String) -> anyhow::Result<()> {
+        let buf = packets::Buffer {
+            text,
+            name: String::new(),
+            sound: String::new(),
+        };
+        self.ctx.outbox.send(self.peer, Data::Buffer(buf));
+        Ok(())
+    }
+    /// Loads a player who isn't online, for commands that act on their account.
+    pub fn load_offline(&self, name: &str) -> anyhow::Result<Option<Player>> {
+        Player::load(self.ctx, name.to_string(), self.peer)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Kind {
+    /// A player who is online.
+    Player,
+    /// Any player with an account, online or not.
+    Account,
+    Integer,
+    /// A time such as `30m` or `1h30m`, handed to the handler in milliseconds.
+    Duration,
+    /// A map that is loaded.
+    Map,
+    /// A name for a new map, it becomes its file name so only letters, digits,
+    /// `_` and `-` are allowed.
+    MapName,
+    /// An item from the item registry.
+    Item,
+    /// A role from server.toml.
+    Role,
+    Word,
+    /// Everything left on the line, it has to be the last argument.
+    Rest,
+}
+
+pub struct Arg {
+    name: &'static str,
+    kind: Kind,
+    optional: bool,
+}
+
+pub const fn required(name: &'static str, kind: Kind) -> Arg {
+    Arg {
+        name,
+        kind,
+        optional: false,
+    }
+}
+
+pub const fn optional(name: &'static str, kind: Kind) -> Arg {
+    Arg {
+        name,
+        kind,
+        optional: true,
+    }
+}
+
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// What a player needs to run the command, None lets everyone run it.
+    pub permission: Option<&'static str>,
+    pub args: &'static [Arg],
+    pub description: &'static str,
+    handler: Handler,
+}
+
+impl Command {
+    pub fn allows(&self, call: &Call) -> bool {
+        self.permission.is_none_or(|p| call.has_permission(p))
+    }
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+        for arg in self.args {
+            let name = match arg.kind {
+                Kind::Rest => format!("{}...", arg.name),
+                _ => arg.name.to_string(),
+            };
+            if arg.optional {
+                usage += &format!(" [{}]", name);
+            } else {
+                usage += &format!(" <{}>", name);
+            }
+        }
+        usage
+    }
+}
+
+pub enum Value {
+    Text(String),
+    Integer(isize),
+    Duration(u64),
+}
+
+/// The arguments of a command after they were checked against its spec,
+/// optional arguments that weren't given are missing from the end.
+pub struct Args {
+    values: Vec<Value>,
+}
+
+impl Args {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn text(&self, index: usize) -> anyhow::Result<&str> {
+        match self.values.get(index) {
+            Some(Value::Text(text)) => Ok(text),
+            _ => anyhow::bail!("Argument {} is not text", index),
+        }
+    }
+    pub fn integer(&self, index: usize) -> anyhow::Result<isize> {
+        match self.values.get(index) {
+            Some(Value::Integer(number)) => Ok(*number),
+            _ => anyhow::bail!("Argument {} is not a number", index),
+        }
+    }
+    pub fn duration(&self, index: usize) -> anyhow::Result<u64> {
+        match self.values.get(index) {
+            Some(Value::Duration(time)) => Ok(*time),
+            _ => anyhow::bail!("Argument {} is not a duration", index),
+        }
+    }
+}
+
+/// Splits off the first word of a line, returns it and the rest of the line.
+fn next_word(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    if line.is_empty() {
+        return None;
+    }
+    let end = line.find(char::is_whitespace).unwrap_or(line.len());
+    Some((&line[..end], &line[end..]))
+}
+
+fn parse_value(call: &mut Call, kind: Kind, text: &str) -> Result<Value, String> {
+    let ctx = &call.ctx;
+    match kind {
+        Kind::Player => match player::find(call.players, text) {
+            Some(player) => Ok(Value::Text(player.name.clone())),
+            None => Err(format!("{} is not online", text)),
+        },
+        Kind::Account => match ctx.storage.account_exists(text) {
+            Ok(true) => Ok(Value::Text(text.to_string())),
+            Ok(false) => Err(format!("There is no player called {}", text)),
+            Err(e) => Err(e.to_string()),
+        },
+        Kind::Integer => match text.parse() {
+            Ok(number) => Ok(Value::Integer(number)),
+            Err(_) => Err(format!("{} is not a number", text)),
+        },
+        Kind::Duration => match parse_time(text) {
+            Some(time) => Ok(Value::Duration(time)),
+            None => Err(format!("{} is not a time, Try 30m, 2h or 1d", text)),
+        },
+        Kind::Map => match ctx.maps.contains_key(text) {
+            true => Ok(Value::Text(text.to_string())),
+            false => Err(format!("There is no map called {}", text)),
+        },
+        Kind::MapName => {
+            let valid = text
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            match valid {
+                true => Ok(Value::Text(text.to_string())),
+                false => Err(format!(
+                    "{} can't be used as a map name, Use letters, numbers, _ and -",
+                    text
+                )),
+            }
+        }
+        Kind::Item => match ctx.item_registry.contains(text) {
+            true => Ok(Value::Text(text.to_string())),
+            false => Err(format!("There is no item called {}", text)),
+        },
+        Kind::Role => match ctx.config.role(text) {
+            Some(_) => Ok(Value::Text(text.to_string())),
+            None => Err(format!("There is no role called {}", text)),
+        },
+        Kind::Word | Kind::Rest => Ok(Value::Text(text.to_string())),
+    }
+}
+
+pub struct CommandRegistry {
+    commands: Vec<&'static Command>,
+    names: HashMap<&'static str, usize>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            commands: vec![],
+            names: HashMap::new(),
+        };
+        for command in general::COMMANDS.iter().chain(admin::COMMANDS) {
+            registry.register(command);
+        }
+        registry
+    }
+    fn register(&mut self, command: &'static Command) {
+        let index = self.commands.len();
+        for name in std::iter::once(&command.name).chain(command.aliases) {
+            if self.names.insert(name, index).is_some() {
+                panic!("The command name /{} is used twice", name);
+            }
+        }
+        self.commands.push(command);
+    }
+    pub fn get(&self, name: &str) -> Option<&'static Command> {
+        let index = self.names.get(name.to_lowercase().as_str())?;
+        Some(self.commands[*index])
+    }
+    /// The commands a player is allowed to run, in the order they were registered.
+    pub fn available<'a>(&'a self, call: &'a Call) -> impl Iterator<Item = &'static Command> + 'a {
+        self.commands.iter().copied().filter(|c| c.allows(call))
+    }
+    fn parse(&self, call: &mut Call, command: &Command, mut line: &str) -> Result<Args, String> {
+        let mut values = vec![];
+        for arg in command.args {
+            let text = match arg.kind {
+                Kind::Rest => {
+                    let rest = line.trim();
+                    line = "";
+                    rest
+                }
+                _ => match next_word(line) {
+                    Some((word, rest)) => {
+                        line = rest;
+                        word
+                    }
+                    None => "",
+                },
+            };
+            if text.is_empty() {
+                if arg.optional {
+                    break;
+                }
+                return Err(format!("Missing {}", arg.name));
+            }
+            match parse_value(call, arg.kind, text) {
+                Ok(value) => values.push(value),
+                Err(e) => return Err(format!("{}: {}", arg.name, e)),
+            }
+        }
+        if !line.trim().is_empty() {
+            return Err(String::from("Too many arguments"));
+        }
+        Ok(Args { values })
+    }
+    /// Runs a command line typed by a player, without the slash.
+    pub fn dispatch(&self, call: &mut Call, line: &str) -> anyhow::Result<()> {
+        let (name, rest) = match next_word(line) {
+            Some(word) => word,
+            None => return Ok(()),
+        };
+        let command = match self.get(name) {
+            Some(command) if command.allows(call) => command,
+            _ => {
+                call.say(format!(
+                    "Unknown command /{}, Type /help for a list of commands",
+                    name
+                ))?;
+                return Ok(());
+            }
+        };
+        match self.parse(call, command, rest) {
+            Ok(args) => (command.handler)(call, &args),
+            Err(e) => call.say(format!("Error: {}, Usage: {}", e, command.usage())),
+        }
+    }
+}
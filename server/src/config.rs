@@ -0,0 +1,337 @@
+use crate::{chat_filter::DenyList, connection::PACKET_KINDS, readable_time::parse_time};
+use indexmap::IndexMap;
+use semver::Version;
+use serde_derive::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+};
+
+const DEFAULT_CONFIG: &str = include_str!("server.toml");
+
+#[derive(Deserialize, Default)]
+pub struct Role {
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+impl Role {
+    /// Whether this role grants a permission, `*` grants all of them and
+    /// `map.*` everything starting with `map.`.
+    pub fn allows(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| match p.strip_suffix('*') {
+            Some(prefix) => permission.starts_with(prefix),
+            None => p == permission,
+        })
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PenaltyAction {
+    Mute,
+    Kick,
+    Ban,
+}
+
+/// A step of the warning ladder, what happens to a player once they have
+/// this many warnings.
+#[derive(Deserialize)]
+pub struct Penalty {
+    pub warnings: usize,
+    pub action: PenaltyAction,
+    /// A time such as `1h` or `1d`, mutes and bans without one last for good.
+    pub duration: Option<String>,
+}
+
+impl Penalty {
+    /// The duration in milliseconds, None if the penalty lasts for good.
+    pub fn time(&self) -> Option<u64> {
+        self.duration.as_deref().and_then(parse_time)
+    }
+}
+
+/// What happens to packets sent faster than their limit allows.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitAction {
+    /// Ignore them.
+    Drop,
+    /// Ignore them and tell the admins.
+    Warn,
+    /// Tell the admins and disconnect the peer.
+    Disconnect,
+}
+
+/// How fast a peer can send one kind of packet.
+#[derive(Deserialize)]
+pub struct Limit {
+    /// Packets that can be sent in a row.
+    pub burst: u32,
+    /// Packets a second after the burst.
+    pub per_second: f64,
+    pub action: LimitAction,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub bind: Ipv4Addr,
+    pub port: u16,
+    pub max_peers: usize,
+    /// Bytes per second, 0 is unlimited.
+    pub incoming_bandwidth: u32,
+    pub outgoing_bandwidth: u32,
+    /// Bad packets a peer can send before it is disconnected, 0 never disconnects.
+    pub max_packet_errors: u32,
+    /// Lets clients that don't do the session handshake in with the shared
+    /// Fernet key, for older clients while they update.
+    pub fernet_transport: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            bind: Ipv4Addr::UNSPECIFIED,
+            port: 18832,
+            max_peers: 100,
+            incoming_bandwidth: 0,
+            outgoing_bandwidth: 0,
+            max_packet_errors: 10,
+            fernet_transport: true,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ChatConfig {
+    /// Messages a player can send in a row before the rate limit applies.
+    pub burst: u32,
+    /// How many messages a player can send a minute after their burst.
+    pub per_minute: u32,
+    /// In characters, 0 is unlimited.
+    pub max_length: usize,
+    /// How long the same message can't be sent again, leave out to allow it.
+    pub duplicate_window: Option<String>,
+    /// Runs of one character longer than this are cut down, 0 leaves them alone.
+    pub max_repeated_characters: usize,
+    /// Messages containing one of these, ignoring case, are blocked.
+    pub deny: Vec<String>,
+    /// Regular expressions blocked messages match.
+    pub deny_patterns: Vec<String>,
+    /// Blocked messages before a player is muted, 0 never mutes.
+    pub violations: usize,
+    pub mute_duration: String,
+}
+
+impl ChatConfig {
+    pub fn duplicate_time(&self) -> Option<u64> {
+        self.duplicate_window.as_deref().and_then(parse_time)
+    }
+    pub fn mute_time(&self) -> u64 {
+        parse_time(&self.mute_duration).unwrap_or(0)
+    }
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            burst: 5,
+            per_minute: 20,
+            max_length: 300,
+            duplicate_window: Some(String::from("30s")),
+            max_repeated_characters: 4,
+            deny: vec![],
+            deny_patterns: vec![],
+            violations: 3,
+            mute_duration: String::from("10m"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct PathConfig {
+    /// Holds the players directory, server.dat and note.txt.
+    pub data: PathBuf,
+    pub maps: PathBuf,
+    /// The client's directory, map ambience files are checked against it when
+    /// maps are loaded or edited.
+    pub client: Option<PathBuf>,
+    /// `files` or `sqlite:<path>`, see `storage::open`.
+    pub storage: String,
+    pub keys: PathBuf,
+    pub weapons: PathBuf,
+    pub items: PathBuf,
+}
+
+impl Default for PathConfig {
+    fn default() -> Self {
+        Self {
+            data: PathBuf::from("."),
+            maps: PathBuf::from("maps"),
+            client: None,
+            storage: String::from("files"),
+            keys: PathBuf::from("keys.toml"),
+            weapons: PathBuf::from("weapons.toml"),
+            items: PathBuf::from("items.toml"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    /// Older clients are told to update.
+    pub minimum_version: String,
+    pub download_url: String,
+    pub linux_download_url: String,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            minimum_version: String::from(env!("CARGO_PKG_VERSION")),
+            download_url: String::from("https://arelius.org/coas/coas.zip"),
+            linux_download_url: String::from("https://arelius.org/coas/coas_linux.zip"),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct ServerConfig {
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub paths: PathConfig,
+    #[serde(default)]
+    pub client: ClientConfig,
+    #[serde(default)]
+    pub chat: ChatConfig,
+    /// How many tiles away sounds can be heard, None sends them to the whole map.
+    pub hearing_distance: Option<isize>,
+    #[serde(default)]
+    pub roles: IndexMap<String, Role>,
+    /// Roles players hold no matter what was granted to them in game.
+    #[serde(default)]
+    pub members: HashMap<String, Vec<String>>,
+    /// Packet rate limits by packet name, packets without one aren't limited.
+    #[serde(default)]
+    pub limits: IndexMap<String, Limit>,
+    /// The warning ladder, ordered by the number of warnings.
+    #[serde(default)]
+    pub penalties: Vec<Penalty>,
+}
+
+impl ServerConfig {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let config: ServerConfig = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            println!(
+                "{} not found, Creating it with the default settings",
+                path.display()
+            );
+            fs::write(path, DEFAULT_CONFIG)?;
+        }
+        let text = fs::read_to_string(path)?;
+        match Self::parse(&text) {
+            Ok(config) => Ok(config),
+            Err(e) => anyhow::bail!("Invalid server config {}: {}", path.display(), e),
+        }
+    }
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.network.max_peers == 0 {
+            anyhow::bail!("max_peers must be at least 1");
+        }
+        if let Err(e) = Version::parse(&self.client.minimum_version) {
+            anyhow::bail!("minimum_version: {}", e);
+        }
+        if self.hearing_distance.is_some_and(|d| d < 0) {
+            anyhow::bail!("hearing_distance can't be negative");
+        }
+        for name in self.roles.keys() {
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                anyhow::bail!("Role name '{}' must be a single word", name);
+            }
+        }
+        for (player, roles) in &self.members {
+            for role in roles {
+                if !self.roles.contains_key(role) {
+                    anyhow::bail!("{}: unknown role {}", player, role);
+                }
+            }
+        }
+        if let Some(window) = &self.chat.duplicate_window {
+            if parse_time(window).is_none() {
+                anyhow::bail!("duplicate_window '{}' is not a valid time", window);
+            }
+        }
+        if parse_time(&self.chat.mute_duration).is_none() {
+            anyhow::bail!(
+                "mute_duration '{}' is not a valid time",
+                self.chat.mute_duration
+            );
+        }
+        if let Err(e) = DenyList::new(&self.chat) {
+            anyhow::bail!("deny_patterns: {}", e);
+        }
+        for (name, limit) in &self.limits {
+            if !PACKET_KINDS.contains(&name.as_str()) {
+                anyhow::bail!("limits: unknown packet {}", name);
+            }
+            if limit.burst == 0 || limit.per_second.is_nan() || limit.per_second <= 0.0 {
+                anyhow::bail!("limits.{}: burst and per_second must be above 0", name);
+            }
+        }
+        let mut warnings = 0;
+        for penalty in &self.penalties {
+            if penalty.warnings <= warnings {
+                anyhow::bail!("penalties must be ordered by a growing number of warnings");
+            }
+            warnings = penalty.warnings;
+            if let Some(duration) = &penalty.duration {
+                if parse_time(duration).is_none() {
+                    anyhow::bail!("penalty duration '{}' is not a valid time", duration);
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn map_path(&self, name: &str) -> PathBuf {
+        self.paths.maps.join(format!("{}.map", name))
+    }
+    pub fn note_path(&self) -> PathBuf {
+        self.paths.data.join("note.txt")
+    }
+    /// The penalty for a player with this many warnings, the highest step
+    /// they reached so every warning past the last step repeats it.
+    pub fn penalty(&self, warnings: usize) -> Option<&Penalty> {
+        self.penalties.iter().rev().find(|p| p.warnings <= warnings)
+    }
+    pub fn role(&self, name: &str) -> Option<&Role> {
+        self.roles.get(name)
+    }
+    /// Every role a player holds, the granted ones first.
+    pub fn roles_of<'a>(
+        &'a self,
+        player: &str,
+        granted: &'a [String],
+    ) -> impl Iterator<Item = &'a String> + 'a {
+        let members = self.members.get(player).into_iter().flatten();
+        granted
+            .iter()
+            .chain(members.filter(|r| !granted.contains(r)))
+    }
+    pub fn allows(&self, player: &str, granted: &[String], permission: &str) -> bool {
+        self.roles_of(player, granted)
+            .filter_map(|r| self.role(r))
+            .any(|r| r.allows(permission))
+    }
+}
@@ -1,17 +1,146 @@
+use crate::{
+    config::Limit,
+    server::{packets::packet::Data, TICK},
+};
+use coas_transport::Session;
 use enet::Peer;
-use std::time::Instant;
+use indexmap::IndexMap;
+use std::collections::HashMap;
+
+/// The packet names used by `[limits]` in server.toml.
+pub const PACKET_KINDS: &[&str] = &[
+    "connect",
+    "connected",
+    "login",
+    "create",
+    "created",
+    "chat",
+    "buffer",
+    "close",
+    "error",
+    "ping",
+    "pong",
+    "who",
+    "offline",
+    "online",
+    "play",
+    "move",
+    "parse_map",
+    "move_client",
+    "say",
+    "weapon_data",
+    "draw",
+    "fire",
+    "reload",
+    "health",
+    "ammo",
+    "fire_stop",
+    "teleport",
+    "cycle",
+    "use_item",
+    "server_stats",
+    "server_note",
+];
+
+/// The name of a packet in `[limits]`, the same as in packets.proto.
+pub fn packet_kind(data: &Data) -> &'static str {
+    match data {
+        Data::Connect(_) => "connect",
+        Data::Connected(_) => "connected",
+        Data::Login(_) => "login",
+        Data::Create(_) => "create",
+        Data::Created(_) => "created",
+        Data::Chat(_) => "chat",
+        Data::Buffer(_) => "buffer",
+        Data::Close(_) => "close",
+        Data::Error(_) => "error",
+        Data::Ping(_) => "ping",
+        Data::Pong(_) => "pong",
+        Data::Who(_) => "who",
+        Data::Offline(_) => "offline",
+        Data::Online(_) => "online",
+        Data::Play(_) => "play",
+        Data::Move(_) => "move",
+        Data::ParseMap(_) => "parse_map",
+        Data::MoveClient(_) => "move_client",
+        Data::Say(_) => "say",
+        Data::WeaponData(_) => "weapon_data",
+        Data::Draw(_) => "draw",
+        Data::Fire(_) => "fire",
+        Data::Reload(_) => "reload",
+        Data::Health(_) => "health",
+        Data::Ammo(_) => "ammo",
+        Data::FireStop(_) => "fire_stop",
+        Data::Teleport(_) => "teleport",
+        Data::Cycle(_) => "cycle",
+        Data::UseItem(_) => "use_item",
+        Data::ServerStats(_) => "server_stats",
+        Data::ServerNote(_) => "server_note",
+    }
+}
+
+/// What spending from a budget came to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spent {
+    Allowed,
+    Over,
+    /// Over for the first time since the budget was last full, the admins
+    /// only hear about that one.
+    FirstOver,
+}
+
+/// The packets of one kind a peer can still send, refilled every tick.
+struct Budget {
+    tokens: f64,
+    alerted: bool,
+}
+
 pub struct Connection {
     pub addr: Peer,
-    pub timer: Instant,
+    /// The game time the peer connected at, see `Context::now`.
+    pub timer: u128,
+    /// Packets from this peer that couldn't be read or handled.
+    pub errors: u32,
+    /// Set once the peer did the handshake, peers without one use Fernet.
+    pub session: Option<Session>,
+    budgets: HashMap<&'static str, Budget>,
 }
 impl Connection {
-    pub fn new(addr: Peer) -> Self {
+    pub fn new(addr: Peer, now: u128) -> Self {
         Self {
             addr,
-            timer: Instant::now(),
+            timer: now,
+            errors: 0,
+            session: None,
+            budgets: HashMap::new(),
+        }
+    }
+    /// Takes a packet of this kind out of its budget.
+    pub fn spend(&mut self, kind: &'static str, limit: &Limit) -> Spent {
+        let budget = self.budgets.entry(kind).or_insert(Budget {
+            tokens: limit.burst as f64,
+            alerted: false,
+        });
+        if budget.tokens >= 1.0 {
+            budget.tokens -= 1.0;
+            return Spent::Allowed;
+        }
+        if budget.alerted {
+            return Spent::Over;
         }
+        budget.alerted = true;
+        Spent::FirstOver
     }
-    pub fn update(&mut self) -> anyhow::Result<()> {
+    pub fn update(&mut self, limits: &IndexMap<String, Limit>) -> anyhow::Result<()> {
+        self.budgets.retain(|kind, budget| {
+            let Some(limit) = limits.get(*kind) else {
+                return false;
+            };
+            let burst = limit.burst as f64;
+            budget.tokens = (budget.tokens + limit.per_second * TICK.as_secs_f64()).min(burst);
+            // Full budgets are dropped, they start out full when used again.
+            budget.tokens < burst
+        });
         Ok(())
     }
 }
@@ -0,0 +1,44 @@
+use crate::readable_time::format_time;
+use chrono::{DateTime, Duration, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// When a ban or a mute ends, saved as the time or null if it lasts until it
+/// is lifted.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(transparent)]
+pub struct Expiry(Option<DateTime<Utc>>);
+
+impl Expiry {
+    /// Ends `time` milliseconds after `start`, times too far away never end.
+    pub fn after(start: DateTime<Utc>, time: u64) -> Self {
+        let time = Duration::milliseconds(time.try_into().unwrap_or(i64::MAX));
+        Self(start.checked_add_signed(time))
+    }
+    pub fn expired(&self, now: DateTime<Utc>) -> bool {
+        self.0.is_some_and(|expires| expires <= now)
+    }
+    /// Milliseconds until it ends, None if it never does.
+    pub fn remaining(&self, now: DateTime<Utc>) -> Option<u128> {
+        let expires = self.0?;
+        Some((expires - now).num_milliseconds().max(0) as u128)
+    }
+}
+
+/// What a banned or muted player is told, e.g. "You have been muted by X,
+/// Reason: r, Time remaining: t".
+pub fn notice(
+    action: &str,
+    issuer: &str,
+    reason: &str,
+    expires: Expiry,
+    now: DateTime<Utc>,
+) -> String {
+    let mut message = format!("You have been {} by {}", action, issuer);
+    if !reason.is_empty() {
+        message += &format!(", Reason: {}", reason);
+    }
+    if let Some(remaining) = expires.remaining(now) {
+        message += &format!(", Time remaining: {}", format_time(remaining));
+    }
+    message
+}
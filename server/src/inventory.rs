@@ -1,3 +1,4 @@
+use crate::item_registry::{ItemDefinition, ItemRegistry};
 use serde_derive::{Deserialize, Serialize};
 
 #[derive(Default, Serialize, Deserialize)]
@@ -12,25 +13,34 @@ pub struct Inventory {
 }
 impl Inventory {
     pub fn cycle(&mut self, direction: usize) {
+        let Some(last) = self.items.len().checked_sub(1) else {
+            return;
+        };
         if direction == 0 {
-            if self.index > 0 {
+            if self.index > 0 && self.index <= last {
                 self.index -= 1;
             } else {
-                self.index = self.items.len() - 1;
+                self.index = last;
             }
         } else if direction == 1 {
-            if self.index < self.items.len() - 1 {
+            if self.index < last {
                 self.index += 1;
             } else {
                 self.index = 0;
             }
         }
     }
-    pub fn get_text(&self) -> String {
-        if let Some(item) = self.get(self.item()) {
+    pub fn get_text(&self, registry: &ItemRegistry) -> String {
+        if let Some(item) = self.item().and_then(|name| self.get(name)) {
+            let mut name = registry.display_name(&item.name);
+            if let Some(definition) = registry.get(&item.name) {
+                if !definition.description.is_empty() {
+                    name = format!("{}, {}", name, definition.description);
+                }
+            }
             return format!(
                 "{}:  You have {}, {} of {}",
-                item.name,
+                name,
                 item.count,
                 self.index + 1,
                 self.items.len()
@@ -38,8 +48,9 @@ impl Inventory {
         }
         String::from("Error")
     }
-    pub fn item(&self) -> &str {
-        &self.items[self.index].name
+    /// The selected item, None when the inventory is empty.
+    pub fn item(&self) -> Option<&str> {
+        self.items.get(self.index).map(|i| i.name.as_str())
     }
     pub fn get(&self, name: &str) -> Option<&InventoryItem> {
         for i in &self.items {
@@ -52,23 +63,37 @@ impl Inventory {
     pub fn is_empty(&self) -> bool {
         self.items.is_empty()
     }
-    pub fn give(&mut self, item_name: &str, amount: isize) {
+    /// Adds or removes an item, capped by its stack limit, returns how many were actually added or removed.
+    pub fn give(&mut self, definition: &ItemDefinition, amount: isize) -> isize {
         for i in 0..self.items.len() {
-            if self.items[i].name == item_name {
-                self.items[i].count += amount;
+            if self.items[i].name == definition.id {
+                let old_count = self.items[i].count;
+                if amount > 0 {
+                    let limit = std::cmp::max(old_count, definition.max_stack);
+                    self.items[i].count = std::cmp::min(old_count + amount, limit);
+                } else {
+                    self.items[i].count += amount;
+                }
                 if self.items[i].count <= 0 {
                     self.items.remove(i);
-                    if self.index != 0 {
-                        self.index -= 1;
+                    // Keep the same item selected, or the one before when the selected one is gone.
+                    if self.index > i || self.index == self.items.len() {
+                        self.index = self.index.saturating_sub(1);
                     }
+                    return -old_count;
                 }
-                return;
+                return self.items[i].count - old_count;
             }
         }
+        if amount <= 0 {
+            return 0;
+        }
         let item = InventoryItem {
-            name: item_name.to_string(),
-            count: amount,
+            name: definition.id.clone(),
+            count: std::cmp::min(amount, definition.max_stack),
         };
+        let given = item.count;
         self.items.push(item);
+        given
     }
 }
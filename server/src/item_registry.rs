@@ -0,0 +1,149 @@
+use crate::weapon_registry::WeaponRegistry;
+use indexmap::IndexMap;
+use serde_derive::Deserialize;
+use std::{fs, path::Path};
+
+const DEFAULT_ITEMS: &str = include_str!("items.toml");
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Consumable,
+    Pickup,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BuffKind {
+    Damage,
+    Defense,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ItemEffect {
+    Heal {
+        amount: isize,
+    },
+    GrantAmmo {
+        weapon: String,
+        amount: isize,
+    },
+    GrantCartridges {
+        weapon: String,
+        amount: isize,
+    },
+    GrantWeapon {
+        weapon: String,
+    },
+    Buff {
+        buff: BuffKind,
+        percent: isize,
+        duration: u64,
+    },
+}
+
+fn default_max_stack() -> isize {
+    1
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ItemDefinition {
+    #[serde(skip)]
+    pub id: String,
+    pub kind: ItemKind,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_max_stack")]
+    pub max_stack: isize,
+    #[serde(default)]
+    pub cooldown: u64,
+    #[serde(default)]
+    pub sound: Option<String>,
+    pub effect: ItemEffect,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ItemRegistry {
+    items: IndexMap<String, ItemDefinition>,
+}
+
+impl ItemRegistry {
+    pub fn parse(text: &str, weapons: &WeaponRegistry) -> anyhow::Result<Self> {
+        let mut registry: ItemRegistry = toml::from_str(text)?;
+        for (id, item) in registry.items.iter_mut() {
+            item.id = id.clone();
+        }
+        registry.validate(weapons)?;
+        Ok(registry)
+    }
+    pub fn load(path: &Path, weapons: &WeaponRegistry) -> anyhow::Result<Self> {
+        if !path.exists() {
+            println!(
+                "{} not found, Creating it with the default items",
+                path.display()
+            );
+            fs::write(path, DEFAULT_ITEMS)?;
+        }
+        let text = fs::read_to_string(path)?;
+        match Self::parse(&text, weapons) {
+            Ok(registry) => Ok(registry),
+            Err(e) => anyhow::bail!("Invalid items file {}: {}", path.display(), e),
+        }
+    }
+    pub fn validate(&self, weapons: &WeaponRegistry) -> anyhow::Result<()> {
+        for (id, item) in &self.items {
+            if id.is_empty() || id.contains(char::is_whitespace) {
+                anyhow::bail!("Item name '{}' must be a single word", id);
+            }
+            if item.name.trim().is_empty() {
+                anyhow::bail!("{}: the display name can't be empty", id);
+            }
+            if item.max_stack < 1 {
+                anyhow::bail!("{}: max_stack must be at least 1", id);
+            }
+            match &item.effect {
+                ItemEffect::Heal { amount } => {
+                    if *amount <= 0 {
+                        anyhow::bail!("{}: heal amount must be positive", id);
+                    }
+                }
+                ItemEffect::GrantAmmo { weapon, amount }
+                | ItemEffect::GrantCartridges { weapon, amount } => {
+                    if !weapons.contains(weapon) {
+                        anyhow::bail!("{}: unknown weapon {}", id, weapon);
+                    }
+                    if *amount <= 0 {
+                        anyhow::bail!("{}: amount must be positive", id);
+                    }
+                }
+                ItemEffect::GrantWeapon { weapon } => {
+                    if !weapons.contains(weapon) {
+                        anyhow::bail!("{}: unknown weapon {}", id, weapon);
+                    }
+                }
+                ItemEffect::Buff {
+                    percent, duration, ..
+                } => {
+                    if *percent <= 0 || *duration == 0 {
+                        anyhow::bail!("{}: buffs need a positive percent and duration", id);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn get(&self, id: &str) -> Option<&ItemDefinition> {
+        self.items.get(id)
+    }
+    pub fn contains(&self, id: &str) -> bool {
+        self.items.contains_key(id)
+    }
+    pub fn display_name(&self, id: &str) -> String {
+        match self.items.get(id) {
+            Some(item) => item.name.clone(),
+            None => id.to_string(),
+        }
+    }
+}
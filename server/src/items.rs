@@ -1,6 +1,5 @@
-use crate::{rotation::get_2d_distance, server::get_server};
+use crate::{player::Players, rotation::get_2d_distance, server::Context};
 use rand::Rng;
-use std::time::Instant;
 
 #[derive(Eq, PartialEq, Debug)]
 pub struct Item {
@@ -8,7 +7,8 @@ pub struct Item {
     y: isize,
     name: String,
     map: String,
-    timer: Instant,
+    /// The game time of the last beep.
+    timer: u128,
 }
 impl Item {
     fn check(&self, x: isize, y: isize) -> bool {
@@ -29,7 +29,8 @@ pub struct ItemSpawner {
     map: String,
     names: Vec<String>,
     items: Vec<Item>,
-    timer: Instant,
+    /// The game time of the last spawn.
+    timer: u128,
     spawn_time: u128,
 }
 impl ItemSpawner {
@@ -53,27 +54,30 @@ impl ItemSpawner {
             map,
             names,
             items: vec![],
-            timer: Instant::now(),
+            timer: 0,
             spawn_time,
         }
     }
-    pub fn update_items(&mut self) -> anyhow::Result<()> {
+    pub fn update_items(&mut self, players: &mut Players, ctx: &mut Context) -> anyhow::Result<()> {
         for i in 0..self.items.len() {
-            if self.items[i].timer.elapsed().as_millis() >= 650 {
-                self.items[i].timer = Instant::now();
-                get_server().play(
+            if ctx.now() - self.items[i].timer >= 650 {
+                self.items[i].timer = ctx.now();
+                ctx.outbox.play(
                     "items/beep.mp3",
                     self.items[i].x,
                     self.items[i].y,
                     &self.items[i].map,
                 )?;
             }
-            for (_, p) in &mut get_server().players {
+            for (_, p) in players.iter_mut() {
                 if p.data.map == self.items[i].map && self.items[i].check(p.data.x, p.data.y) {
-                    p.give(&self.items[i].name, 1)?;
-                    p.play("items/gather.mp3".to_string())?;
+                    if p.give(ctx, &self.items[i].name, 1)? == 0 {
+                        continue;
+                    }
+                    p.play(ctx, "items/gather.mp3".to_string())?;
                     self.count -= 1;
-                    p.say(self.items[i].name.clone())?;
+                    let name = ctx.item_registry.display_name(&self.items[i].name);
+                    p.say(ctx, name)?;
                     self.items.remove(i);
                     return Ok(());
                 }
@@ -81,23 +85,26 @@ impl ItemSpawner {
         }
         Ok(())
     }
-    pub fn update(&mut self) -> anyhow::Result<()> {
-        if self.timer.elapsed().as_millis() >= self.spawn_time && self.count < self.maximum {
-            self.timer = Instant::now();
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+    pub fn update(&mut self, players: &mut Players, ctx: &mut Context) -> anyhow::Result<()> {
+        if ctx.now() - self.timer >= self.spawn_time && self.count < self.maximum {
+            self.timer = ctx.now();
             self.count += 1;
-            let name = self.names[get_server().rng.gen_range(0..self.names.len())].clone();
-            let x = get_server().rng.gen_range(self.min_x..=self.max_x);
-            let y = get_server().rng.gen_range(self.min_y..=self.max_y);
+            let name = self.names[ctx.rng.gen_range(0..self.names.len())].clone();
+            let x = ctx.rng.gen_range(self.min_x..=self.max_x);
+            let y = ctx.rng.gen_range(self.min_y..=self.max_y);
             let item = Item {
                 x,
                 y,
                 name,
                 map: self.map.clone(),
-                timer: Instant::now(),
+                timer: ctx.now(),
             };
             self.items.push(item);
         }
-        self.update_items()?;
+        self.update_items(players, ctx)?;
         Ok(())
     }
 }
@@ -0,0 +1,60 @@
+use fernet::Fernet;
+use serde_derive::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+/// The Fernet keys the server encrypts with, kept out of the source in their own file.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Keys {
+    /// Encrypts packets of clients using the Fernet transport, it has to be the
+    /// key the client was built with.
+    pub transport: String,
+    /// Encrypts the account files of the files storage.
+    pub accounts: String,
+    /// Encrypts player data and server.dat in the files storage.
+    pub data: String,
+}
+
+impl Keys {
+    /// New random keys, for servers without existing data or Fernet clients.
+    pub fn generate() -> Self {
+        Self {
+            transport: Fernet::generate_key(),
+            accounts: Fernet::generate_key(),
+            data: Fernet::generate_key(),
+        }
+    }
+    /// Reads the keys, the file has to be created first with the generate-keys
+    /// or import-keys commands.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            anyhow::bail!(
+                "{} not found, Create it with the generate-keys command, or import-keys to keep using the keys of an existing server",
+                path.display()
+            );
+        }
+        let keys: Keys = toml::from_str(&fs::read_to_string(path)?)?;
+        for (name, key) in [
+            ("transport", &keys.transport),
+            ("accounts", &keys.accounts),
+            ("data", &keys.data),
+        ] {
+            if Fernet::new(key).is_none() {
+                anyhow::bail!(
+                    "{}: the {} key is not a valid Fernet key",
+                    path.display(),
+                    name
+                );
+            }
+        }
+        Ok(keys)
+    }
+    /// Writes the keys to a new file, existing keys are never overwritten since
+    /// everything encrypted with them would be lost.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if path.exists() {
+            anyhow::bail!("{} already exists", path.display());
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
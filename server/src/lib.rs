@@ -0,0 +1,38 @@
+//! The Contacts on all sides game server.
+//!
+//! [`Server`] owns the network host, the players and the [`Context`] with the
+//! rest of the game state, the `server` binary only parses the command line and
+//! polls it. Tests and tools can run their own server in process the same way.
+pub mod bans;
+mod chat_filter;
+pub mod cli;
+mod commands;
+pub mod config;
+mod connection;
+mod dm;
+pub mod expiry;
+pub mod inventory;
+mod item_registry;
+mod items;
+pub mod keys;
+pub mod maps;
+mod movement;
+pub mod mutes;
+pub mod outbox;
+mod password;
+pub mod player;
+mod readable_time;
+mod rotation;
+pub mod server;
+pub mod storage;
+mod timer;
+pub mod warnings;
+mod weapon;
+mod weapon_registry;
+
+pub use bans::Bans;
+pub use config::ServerConfig;
+pub use inventory::Inventory;
+pub use maps::grid::Grid;
+pub use player::Player;
+pub use server::{packets, Context, Server};
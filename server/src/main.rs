@@ -1,18 +1,6 @@
-mod bans;
-mod connection;
-mod dm;
-mod inventory;
-mod items;
-mod maps;
-mod player;
-mod readable_time;
-mod rotation;
-mod server;
-mod timer;
-mod weapon;
+use clap::Parser;
+use coas_server::cli::Cli;
+
 fn main() -> anyhow::Result<()> {
-    let sr = server::get_server();
-    loop {
-        sr.poll()?;
-    }
+    Cli::parse().run()
 }
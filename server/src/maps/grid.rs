@@ -1,42 +1,86 @@
-use super::{safe_zone::SafeZone, tile::Tile};
-use crate::items::ItemSpawner;
+use crate::{item_registry::ItemRegistry, items::ItemSpawner, player::Players, server::Context};
+use coas_map::{Directive, MapFile, Rect, RectIndex, Teleporter};
 #[derive(Default)]
 pub struct Grid {
     pub max_x: usize,
     pub max_y: usize,
-    tiles: Vec<Tile>,
+    tiles: RectIndex<String>,
     pub item_spawner: Vec<ItemSpawner>,
-    safe_zones: Vec<SafeZone>,
+    safe_zones: RectIndex<()>,
+    teleporters: RectIndex<Teleporter>,
     pub name: String,
 }
 impl Grid {
-    pub fn get_tile(&self, x: isize, y: isize) -> Option<String> {
-        for i in self.tiles.iter().rev() {
-            if i.in_range(x, y) {
-                return Some(i.tile.clone());
+    pub fn from_file(file: &MapFile) -> anyhow::Result<Self> {
+        let mut map = Grid {
+            name: file.name().unwrap_or_default().to_string(),
+            max_x: file.max_x().unwrap_or_default().try_into()?,
+            max_y: file.max_y().unwrap_or_default().try_into()?,
+            ..Default::default()
+        };
+        for directive in file.directives() {
+            match directive {
+                Directive::Tile { area, tile } => {
+                    map.add(area.min_x, area.max_x, area.min_y, area.max_y, tile);
+                }
+                Directive::SafeZone(area) => {
+                    map.add_safe_zone(area.min_x, area.max_x, area.min_y, area.max_y);
+                }
+                Directive::Teleporter(teleporter) => {
+                    map.teleporters.insert(teleporter.area, teleporter.clone());
+                }
+                Directive::Items {
+                    area,
+                    maximum,
+                    spawn_time,
+                    names,
+                } => {
+                    map.item_spawner.push(ItemSpawner::new(
+                        area.min_x,
+                        area.max_x,
+                        area.min_y,
+                        area.max_y,
+                        *maximum,
+                        *spawn_time,
+                        names.clone(),
+                        map.name.clone(),
+                    ));
+                }
+                _ => {}
             }
         }
-        None
+        Ok(map)
+    }
+    pub fn get_tile(&self, x: isize, y: isize) -> Option<String> {
+        self.tiles.get(x, y).cloned()
     }
     pub fn get_safe_zone(&self, x: isize, y: isize) -> bool {
-        for i in &self.safe_zones {
-            if i.in_range(x, y) {
-                return true;
-            }
-        }
-        false
+        self.safe_zones.contains(x, y)
+    }
+    pub fn get_teleporter(&self, x: isize, y: isize) -> Option<&Teleporter> {
+        self.teleporters.get(x, y)
     }
     pub fn add(&mut self, min_x: isize, max_x: isize, min_y: isize, max_y: isize, tile: &str) {
-        let t = Tile::new(min_x, max_x, min_y, max_y, tile);
-        self.tiles.push(t);
+        self.tiles
+            .insert(Rect::new(min_x, max_x, min_y, max_y), tile.to_string());
     }
     pub fn add_safe_zone(&mut self, min_x: isize, max_x: isize, min_y: isize, max_y: isize) {
-        let s = SafeZone::new(min_x, max_x, min_y, max_y);
-        self.safe_zones.push(s);
+        self.safe_zones
+            .insert(Rect::new(min_x, max_x, min_y, max_y), ());
+    }
+    pub fn check_items(&self, registry: &ItemRegistry) -> anyhow::Result<()> {
+        for spawner in &self.item_spawner {
+            for name in spawner.names() {
+                if !registry.contains(name) {
+                    anyhow::bail!("Map {} spawns an unknown item: {}", self.name, name);
+                }
+            }
+        }
+        Ok(())
     }
-    pub fn update(&mut self) -> anyhow::Result<()> {
+    pub fn update(&mut self, players: &mut Players, ctx: &mut Context) -> anyhow::Result<()> {
         for i in &mut self.item_spawner {
-            i.update()?;
+            i.update(players, ctx)?;
         }
         Ok(())
     }
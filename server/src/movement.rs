@@ -0,0 +1,177 @@
+use crate::maps::grid::Grid;
+use std::fmt;
+
+// These mirror the client's movement timing in client/src/player.rs.
+/// Milliseconds between steps on the ground.
+const WALK_TIME: u64 = 180;
+/// Milliseconds between steps while in the air.
+const AIR_WALK_TIME: u64 = 80;
+/// Milliseconds between two gravity moves.
+const GRAVITY_TIME: u64 = 70;
+/// How many tiles a jump goes up.
+const GRAVITY_MAX: usize = 5;
+/// Milliseconds moves can arrive early before they are too fast, covers network jitter.
+const TOLERANCE: u128 = 500;
+/// Steps a player can take in the air without falling.
+const MAX_AIR_STEPS: usize = 6;
+/// Rejected moves within `VIOLATION_WINDOW` before the admins are told.
+const VIOLATION_LIMIT: usize = 5;
+const VIOLATION_WINDOW: u128 = 30000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    TooFar,
+    OutOfBounds,
+    Wall,
+    NoFooting,
+    Flying,
+    TooFast,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Violation::TooFar => "moved more than one tile",
+            Violation::OutOfBounds => "moved outside the map",
+            Violation::Wall => "walked into a wall",
+            Violation::NoFooting => "climbed without a tile",
+            Violation::Flying => "stayed in the air",
+            Violation::TooFast => "moved too fast",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Limits how often something can happen while allowing bursts of up to
+/// `TOLERANCE`, so packets arriving close together after a lag spike are fine
+/// but a sustained higher rate is not. The times are game times, see
+/// `Context::now`.
+struct Pace {
+    next: u128,
+}
+
+impl Pace {
+    fn new(now: u128) -> Self {
+        Self { next: now }
+    }
+    fn allow(&mut self, now: u128, interval: u64) -> bool {
+        if self.next > now + TOLERANCE {
+            return false;
+        }
+        self.next = self.next.max(now) + interval as u128;
+        true
+    }
+}
+
+/// Checks the moves a client reports against its map, the client is told to
+/// go back when a move is rejected.
+pub struct Movement {
+    steps: Pace,
+    gravity: Pace,
+    /// Tiles gone up since the player last stood on something.
+    rise: usize,
+    /// Steps taken in the air since the player last went down.
+    air_steps: usize,
+    violations: usize,
+    last_violation: u128,
+}
+
+impl Movement {
+    pub fn new(now: u128) -> Self {
+        Self {
+            steps: Pace::new(now),
+            gravity: Pace::new(now),
+            rise: 0,
+            air_steps: 0,
+            violations: 0,
+            last_violation: now,
+        }
+    }
+    /// Forgets the air state, for when the server moves the player itself.
+    pub fn reset(&mut self) {
+        self.rise = 0;
+        self.air_steps = 0;
+    }
+    /// Checks a move from one position to another at game time `now`, silent
+    /// moves are gravity.
+    pub fn check(
+        &mut self,
+        map: &Grid,
+        now: u128,
+        from: (isize, isize),
+        to: (isize, isize),
+        silent: bool,
+    ) -> Result<(), Violation> {
+        // Clients can send any coordinate, so this must not overflow.
+        let (dx, dy) = (to.0.saturating_sub(from.0), to.1.saturating_sub(from.1));
+        if dx == 0 && dy == 0 {
+            return Ok(());
+        }
+        if dx.unsigned_abs().saturating_add(dy.unsigned_abs()) > 1 {
+            return Err(Violation::TooFar);
+        }
+        if to.0 < 0 || to.0 > map.max_x as isize || to.1 < 0 {
+            return Err(Violation::OutOfBounds);
+        }
+        let tile = map.get_tile(to.0, to.1);
+        // Like in the client walls stop steps and jumps, but players can fall onto them.
+        let wall = tile.as_ref().is_some_and(|t| t.contains("wall"));
+        if wall && !(silent && dy < 0) {
+            return Err(Violation::Wall);
+        }
+        let airborne = !on_ground(map, from);
+        if silent {
+            if dx != 0 {
+                return Err(Violation::TooFar);
+            }
+            if dy > 0 && self.rise >= GRAVITY_MAX {
+                return Err(Violation::Flying);
+            }
+            if !self.gravity.allow(now, GRAVITY_TIME) {
+                return Err(Violation::TooFast);
+            }
+        } else {
+            if dy != 0 && tile.is_none() {
+                return Err(Violation::NoFooting);
+            }
+            if dx != 0 && airborne && self.air_steps >= MAX_AIR_STEPS {
+                return Err(Violation::Flying);
+            }
+            let interval = if airborne { AIR_WALK_TIME } else { WALK_TIME };
+            if !self.steps.allow(now, interval) {
+                return Err(Violation::TooFast);
+            }
+        }
+        if dy > 0 && silent {
+            self.rise += 1;
+        }
+        if dy < 0 {
+            self.air_steps = 0;
+        } else if dx != 0 && airborne {
+            self.air_steps += 1;
+        }
+        if on_ground(map, to) && !(silent && dy > 0) {
+            self.reset();
+        }
+        Ok(())
+    }
+    /// Counts a rejected move, returns true when there have been enough of them
+    /// recently that the admins should know.
+    pub fn violation(&mut self, now: u128) -> bool {
+        if now - self.last_violation >= VIOLATION_WINDOW {
+            self.violations = 0;
+        }
+        self.last_violation = now;
+        self.violations += 1;
+        if self.violations >= VIOLATION_LIMIT {
+            self.violations = 0;
+            return true;
+        }
+        false
+    }
+}
+
+/// Players stand on tiles or on the bottom of the map.
+fn on_ground(map: &Grid, (x, y): (isize, isize)) -> bool {
+    y == 0 || map.get_tile(x, y).is_some()
+}
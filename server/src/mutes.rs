@@ -0,0 +1,38 @@
+use crate::expiry::{self, Expiry};
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// Keeps a player from chatting until it expires or is lifted with /unmute.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Mute {
+    pub issuer: String,
+    pub reason: String,
+    pub expires: Expiry,
+    /// Silent mutes are only announced to admins.
+    #[serde(default)]
+    pub silent: bool,
+}
+
+impl Mute {
+    pub fn new(issuer: &str, reason: &str) -> Self {
+        Self {
+            issuer: issuer.to_string(),
+            reason: reason.to_string(),
+            expires: Expiry::default(),
+            silent: false,
+        }
+    }
+    /// Makes the mute end `time` milliseconds from now.
+    pub fn lasting(mut self, time: u64) -> Self {
+        self.expires = Expiry::after(Utc::now(), time);
+        self
+    }
+    pub fn silent(mut self, silent: bool) -> Self {
+        self.silent = silent;
+        self
+    }
+    /// What the muted player is told when they try to chat.
+    pub fn message(&self, now: DateTime<Utc>) -> String {
+        expiry::notice("muted", &self.issuer, &self.reason, self.expires, now)
+    }
+}
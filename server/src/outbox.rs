@@ -0,0 +1,96 @@
+use crate::server::{packets, packets::packet::Data};
+use enet::Peer;
+
+/// Who a queued packet goes to, worked out when the outbox is flushed.
+pub enum Audience {
+    Peer(Peer),
+    All,
+    /// The players on a map.
+    Map(String),
+    /// The players on a map close enough to hear a sound at x, y.
+    Sound {
+        map: String,
+        x: isize,
+        y: isize,
+    },
+    /// The players holding a permission.
+    Permission(&'static str),
+}
+
+pub enum Outgoing {
+    Packet(Audience, Data),
+    /// Sends a player where everyone else is, movement on other maps isn't
+    /// sent so this is needed after changing maps.
+    Positions(Peer),
+    /// Disconnects a peer once the packets queued before are sent.
+    Disconnect(Peer),
+}
+
+/// Packets waiting to be sent. Game code queues them here instead of sending
+/// directly so it never needs the network or the other players to do so, the
+/// server sends them in order once the code is done.
+#[derive(Default)]
+pub struct Outbox {
+    queue: Vec<Outgoing>,
+}
+
+impl Outbox {
+    pub fn take(&mut self) -> Vec<Outgoing> {
+        std::mem::take(&mut self.queue)
+    }
+    pub fn to(&mut self, audience: Audience, data: Data) {
+        self.queue.push(Outgoing::Packet(audience, data));
+    }
+    pub fn send(&mut self, peer: Peer, data: Data) {
+        self.to(Audience::Peer(peer), data);
+    }
+    pub fn broadcast(&mut self, data: Data) {
+        self.to(Audience::All, data);
+    }
+    /// Sends a packet to the players on a map, used for movement.
+    pub fn broadcast_map(&mut self, data: Data, map: &str) {
+        self.to(Audience::Map(map.to_string()), data);
+    }
+    /// Sends a sound to the players on a map close enough to hear it,
+    /// everyone on the map hears it when `hearing_distance` isn't set.
+    pub fn broadcast_sound(&mut self, data: Data, map: &str, x: isize, y: isize) {
+        let map = map.to_string();
+        self.to(Audience::Sound { map, x, y }, data);
+    }
+    pub fn positions(&mut self, peer: Peer) {
+        self.queue.push(Outgoing::Positions(peer));
+    }
+    pub fn disconnect(&mut self, peer: Peer) {
+        self.queue.push(Outgoing::Disconnect(peer));
+    }
+    pub fn say(&mut self, peer: Peer, text: String) {
+        self.send(peer, Data::Say(packets::Say { text }));
+    }
+    pub fn play(&mut self, sound: &str, x: isize, y: isize, map: &str) -> anyhow::Result<()> {
+        let play = packets::Play {
+            x: Some(x.try_into()?),
+            y: Some(y.try_into()?),
+            map: map.to_string(),
+            sound: sound.to_string(),
+            ..Default::default()
+        };
+        self.broadcast_sound(Data::Play(play), map, x, y);
+        Ok(())
+    }
+    pub fn notify(&mut self, text: String) {
+        let note = packets::Buffer {
+            text,
+            name: String::from("notifications"),
+            sound: String::from("notifications/alert.mp3"),
+        };
+        self.broadcast(Data::Buffer(note));
+    }
+    pub fn admin_tell(&mut self, text: String) {
+        let buf = packets::Buffer {
+            text,
+            name: String::from("admin alerts"),
+            sound: String::from("notifications/admin_tell.mp3"),
+        };
+        self.to(Audience::Permission("admin.chat"), Data::Buffer(buf));
+    }
+}
@@ -0,0 +1,52 @@
+use crate::storage::{Account, Storage};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use rand::rngs::OsRng;
+
+/// Hashes a password with Argon2id and a random salt, the result is a PHC
+/// string holding the parameters and salt alongside the hash.
+pub fn hash(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    match Argon2::default().hash_password(password.as_bytes(), &salt) {
+        Ok(hash) => Ok(hash.to_string()),
+        Err(e) => anyhow::bail!("Unable to hash the password: {}", e),
+    }
+}
+
+pub fn verify(password: &str, hash: &str) -> anyhow::Result<bool> {
+    let parsed = match PasswordHash::new(hash) {
+        Ok(parsed) => parsed,
+        Err(e) => anyhow::bail!("Invalid password hash: {}", e),
+    };
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok())
+}
+
+/// Replaces the plaintext password of an account with its hash.
+/// Returns false if the account was already hashed.
+pub fn upgrade(account: &mut Account) -> anyhow::Result<bool> {
+    match account.password.take() {
+        Some(password) => {
+            account.password_hash = Some(hash(&password)?);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Hashes the plaintext password of every account, returns how many were upgraded.
+pub fn migrate(storage: &dyn Storage) -> anyhow::Result<usize> {
+    let mut count = 0;
+    for name in storage.account_names()? {
+        if let Some(mut account) = storage.load_account(&name)? {
+            if upgrade(&mut account)? {
+                storage.save_account(&name, &account)?;
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
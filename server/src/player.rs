@@ -1,35 +1,40 @@
 use crate::{
-    dm::Dm,
+    chat_filter::ChatFilter,
+    config::ServerConfig,
     inventory::Inventory,
-    server::{get_server, packets},
+    item_registry::{BuffKind, ItemDefinition, ItemEffect, ItemKind},
+    maps,
+    movement::{Movement, Violation},
+    mutes::Mute,
+    password,
+    readable_time::format_time,
+    server::{packets, Context},
+    storage::Account,
     timer::Timer,
+    warnings::Warning,
     weapon::Weapon,
+    weapon_registry::WeaponRegistry,
 };
+use chrono::Utc;
+use coas_map::{lint, Coordinate};
 use enet::Peer;
-use fernet::Fernet;
-use rand::Rng;
+use rand::{rngs::ThreadRng, Rng};
 use serde_derive::*;
-use std::{collections::HashMap, convert::From, fs, io::Read, path::Path, time::Instant};
-fn default_weapons() -> HashMap<String, isize> {
-    let mut w = HashMap::new();
-    w.insert(String::from("pistol"), 1);
-    w.insert(String::from("machinegun"), 1);
-    w.insert(String::from("grenade_launcher"), 1);
-    w
-}
-fn default_ammo() -> HashMap<String, isize> {
-    let mut w = HashMap::new();
-    w.insert(String::from("pistol"), 12);
-    w.insert(String::from("machinegun"), 50);
-    w.insert(String::from("grenade_launcher"), 1);
-    w
+use std::{collections::HashMap, convert::From, net::Ipv4Addr};
+
+pub type Players = HashMap<Peer, Player>;
+
+/// Finds an online player by name, ignoring case.
+pub fn find<'a>(players: &'a mut Players, name: &str) -> Option<&'a mut Player> {
+    players
+        .values_mut()
+        .find(|i| i.name.to_lowercase() == name.to_lowercase())
 }
-fn default_cartridges() -> HashMap<String, isize> {
-    let mut w = HashMap::new();
-    w.insert(String::from("pistol"), 50);
-    w.insert(String::from("machinegun"), 100);
-    w.insert(String::from("grenade_launcher"), 35);
-    w
+fn destination(rng: &mut ThreadRng, coordinate: &Coordinate) -> isize {
+    match coordinate {
+        Coordinate::Fixed(value) => *value,
+        Coordinate::Range(range) => rng.gen_range(range.clone()),
+    }
 }
 
 fn default_health() -> isize {
@@ -45,12 +50,17 @@ fn default_hit_ping() -> bool {
 pub struct PlayerData {
     pub x: isize,
     pub y: isize,
+    /// Roles granted in game, the ones from server.toml are added on top.
     #[serde(default)]
-    pub dev: bool,
-    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Only read from saves made before roles existed, see `Player::load`.
+    #[serde(default, skip_serializing)]
     pub admin: bool,
     #[serde(default)]
     pub id: String,
+    /// Where the player last logged in from, for IP bans while they are offline.
+    #[serde(default)]
+    pub ip: Option<Ipv4Addr>,
     pub direction: usize,
     pub map: String,
     #[serde(default)]
@@ -61,60 +71,64 @@ pub struct PlayerData {
     pub last_hit: String,
     #[serde(default, skip_serializing)]
     pub weapon: String,
-    #[serde(default = "default_weapons")]
+    #[serde(default)]
     pub weapons: HashMap<String, isize>,
-    #[serde(default = "default_ammo")]
+    #[serde(default)]
     pub ammo: HashMap<String, isize>,
-    #[serde(default = "default_cartridges")]
+    #[serde(default)]
     pub cartridges: HashMap<String, isize>,
     #[serde(default)]
     pub kills: usize,
     #[serde(default)]
     pub deaths: usize,
     #[serde(default)]
-    pub potion_timer: Timer,
+    pub cooldowns: HashMap<String, Timer>,
     #[serde(default = "default_hit_ping")]
     pub hit_ping: bool,
     #[serde(default)]
     pub agreed_to_rules: bool,
     #[serde(default)]
     pub inventory: Inventory,
-    #[serde(default = "default_can_chat")]
+    /// Only read from saves made before mutes had reasons, see `Player::load`.
+    #[serde(default = "default_can_chat", skip_serializing)]
     pub can_chat: bool,
+    #[serde(default)]
+    pub mute: Option<Mute>,
+    #[serde(default)]
+    pub warnings: Vec<Warning>,
 }
 impl PlayerData {
-    pub fn new() -> Self {
+    pub fn new(registry: &WeaponRegistry) -> Self {
         Self {
             x: 0,
             y: 0,
-            dev: false,
+            roles: vec![],
             admin: false,
             id: String::new(),
+            ip: None,
             direction: 0,
             map: String::from("main"),
             safe: false,
             health: default_health(),
             can_chat: true,
+            mute: None,
+            warnings: vec![],
             hit_ping: true,
             agreed_to_rules: false,
             last_hit: String::new(),
             weapon: String::new(),
-            weapons: default_weapons(),
+            weapons: registry.starting_weapons(),
             kills: 0,
             deaths: 0,
-            potion_timer: Timer::new(),
+            cooldowns: HashMap::new(),
             inventory: Inventory::default(),
-            ammo: default_ammo(),
-            cartridges: default_cartridges(),
+            ammo: registry.starting_ammo(),
+            cartridges: registry.starting_cartridges(),
         }
     }
-    pub fn get_weapon_total_ammo(&self) -> isize {
-        if self.weapon == "pistol" {
-            return 12;
-        } else if self.weapon == "machinegun" {
-            return 50;
-        } else if self.weapon == "grenade_launcher" {
-            return 1;
+    pub fn get_weapon_total_ammo(&self, registry: &WeaponRegistry) -> isize {
+        if let Some(weapon) = registry.get(&self.weapon) {
+            return weapon.magazine;
         }
         0
     }
@@ -171,165 +185,267 @@ pub struct Player {
     pub packet: Option<String>,
     pub data: PlayerData,
     pub got_hit: bool,
-    pub cheat_timer: Instant,
+    /// The timers hold the game time they were last restarted at, see `Context::now`.
+    pub cheat_timer: u128,
     pub cheat_time: u128,
     pub reloading: bool,
     pub reload_time: u128,
-    pub reload_timer: Instant,
+    pub reload_timer: u128,
     pub firing: bool,
     pub fire_time: u128,
-    pub fire_timer: Instant,
+    pub fire_timer: u128,
     pub automatic: bool,
+    pub buffs: Vec<Buff>,
+    pub movement: Movement,
+    pub chat: ChatFilter,
+}
+
+pub struct Buff {
+    pub kind: BuffKind,
+    pub percent: isize,
+    pub name: String,
+    pub timer: u128,
+    pub duration: u128,
 }
 
 impl Player {
-    pub fn new(name: String, addr: Peer) -> Self {
+    pub fn new(name: String, addr: Peer, ctx: &Context) -> Self {
         Self {
             name,
             addr,
             packet: None,
-            data: PlayerData::new(),
+            data: PlayerData::new(&ctx.weapon_registry),
             got_hit: false,
-            cheat_timer: Instant::now(),
+            cheat_timer: ctx.now(),
             cheat_time: 30000,
             reloading: false,
             reload_time: 0,
-            reload_timer: Instant::now(),
+            reload_timer: ctx.now(),
             firing: false,
             fire_time: 0,
-            fire_timer: Instant::now(),
+            fire_timer: ctx.now(),
             automatic: false,
+            buffs: vec![],
+            movement: Movement::new(ctx.now()),
+            chat: ChatFilter::new(&ctx.config.chat, ctx.now()),
         }
     }
-    pub fn use_item(&mut self) -> anyhow::Result<()> {
-        if self.data.inventory.is_empty() {
-            self.say("Empty".to_string())?;
-            return Ok(());
-        }
-        let item = self.data.inventory.item().to_string();
-        if item == "health_potion" {
-            let elapsed = self.data.potion_timer.elapsed();
-            if elapsed < 30000 {
-                self.say(format!(
-                    "You can't use this, You need to wait {} seconds",
-                    ((elapsed as i64 / 1000) - 30).abs()
-                ))?;
+    pub fn use_item(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+        let item = match self.data.inventory.item() {
+            Some(item) => item.to_string(),
+            None => {
+                self.say(ctx, "Empty".to_string())?;
+                return Ok(());
+            }
+        };
+        let definition = match ctx.item_registry.get(&item) {
+            Some(definition) => definition.clone(),
+            None => {
+                self.say(ctx, format!("{} can't be used", item))?;
                 return Ok(());
             }
-            if self.data.health == default_health() {
-                self.say("You are already at full health".to_string())?;
+        };
+        if let Some(timer) = self.data.cooldowns.get(&item) {
+            let elapsed = timer.elapsed();
+            if elapsed < definition.cooldown {
+                self.say(
+                    ctx,
+                    format!(
+                        "You can't use this, You need to wait {} seconds",
+                        (definition.cooldown - elapsed).div_ceil(1000)
+                    ),
+                )?;
                 return Ok(());
             }
-            self.data.potion_timer.restart();
-            self.play("player/potion.mp3".to_string())?;
-            self.data.health = std::cmp::min(self.data.health + 500, default_health());
         }
-        self.give(&item, -1)?;
+        if !self.apply_effect(ctx, &definition, 1)? {
+            return Ok(());
+        }
+        if definition.cooldown > 0 {
+            self.data
+                .cooldowns
+                .entry(item.clone())
+                .or_default()
+                .restart();
+        }
+        if let Some(sound) = &definition.sound {
+            self.play(ctx, sound.clone())?;
+        }
+        self.give(ctx, &item, -1)?;
         Ok(())
     }
-    pub fn cycle(&mut self, direction: usize) -> anyhow::Result<()> {
+    /// Applies an item's effect count times, returns false if it had nothing to do.
+    pub fn apply_effect(
+        &mut self,
+        ctx: &mut Context,
+        definition: &ItemDefinition,
+        count: isize,
+    ) -> anyhow::Result<bool> {
+        match &definition.effect {
+            ItemEffect::Heal { amount } => {
+                if count <= 0 {
+                    return Ok(false);
+                }
+                if self.data.health >= default_health() {
+                    self.say(ctx, "You are already at full health".to_string())?;
+                    return Ok(false);
+                }
+                self.data.health =
+                    std::cmp::min(self.data.health + amount * count, default_health());
+            }
+            ItemEffect::GrantAmmo { weapon, amount } => {
+                self.data.take_ammo(weapon.clone(), amount * count);
+            }
+            ItemEffect::GrantCartridges { weapon, amount } => {
+                self.data.take_cartridge(weapon.clone(), amount * count);
+            }
+            ItemEffect::GrantWeapon { weapon } => {
+                self.data.take_weapon(weapon.clone(), count);
+            }
+            ItemEffect::Buff {
+                buff,
+                percent,
+                duration,
+            } => {
+                if count <= 0 {
+                    return Ok(false);
+                }
+                self.buffs.push(Buff {
+                    kind: *buff,
+                    percent: *percent,
+                    name: definition.name.clone(),
+                    timer: ctx.now(),
+                    duration: (*duration).into(),
+                });
+                self.say(ctx, format!("{} is now active", definition.name))?;
+            }
+        }
+        Ok(true)
+    }
+    pub fn buff_percent(&self, kind: BuffKind) -> isize {
+        self.buffs
+            .iter()
+            .filter(|b| b.kind == kind)
+            .map(|b| b.percent)
+            .sum()
+    }
+    pub fn take_damage(&mut self, damage: isize) {
+        let defense = std::cmp::min(self.buff_percent(BuffKind::Defense), 100);
+        self.data.health -= damage * (100 - defense) / 100;
+    }
+    pub fn cycle(&mut self, ctx: &mut Context, direction: usize) -> anyhow::Result<()> {
         if self.data.inventory.is_empty() {
-            self.say("Empty".to_string())?;
+            self.say(ctx, "Empty".to_string())?;
             return Ok(());
         }
         self.data.inventory.cycle(direction);
-        self.say(self.data.inventory.get_text())?;
+        let text = self.data.inventory.get_text(&ctx.item_registry);
+        self.say(ctx, text)?;
         Ok(())
     }
-    pub fn give(&mut self, item: &str, amount: isize) -> anyhow::Result<()> {
+    /// Gives or takes an item, returns how many were actually received.
+    pub fn give(&mut self, ctx: &mut Context, item: &str, amount: isize) -> anyhow::Result<isize> {
         if amount == 0 {
-            return Ok(());
+            return Ok(0);
         }
-        if item.starts_with("weapon_") {
-            self.data.take_weapon(item.replace("weapon_", ""), amount);
-        } else if item.starts_with("cartridge_") {
-            self.data
-                .take_cartridge(item.replace("cartridge_", ""), amount);
-        } else if item.starts_with("ammo_") {
-            self.data.take_ammo(item.replace("ammo_", ""), amount);
-        } else {
-            self.data.inventory.give(item, amount);
-            let mut buffer = packets::Buffer::default();
-            if amount >= 0 {
-                buffer.text = format!("You gained {} {}", amount, item);
-            } else if amount < 0 {
-                buffer.text = format!("You lost {} {}", amount.abs(), item);
+        let definition = match ctx.item_registry.get(item) {
+            Some(definition) => definition.clone(),
+            None => return Ok(0),
+        };
+        if definition.kind == ItemKind::Pickup {
+            // Pickups take effect right away, so there is nothing to take back.
+            if amount < 0 || !self.apply_effect(ctx, &definition, amount)? {
+                return Ok(0);
             }
-            get_server().send(self.addr, packets::packet::Data::Buffer(buffer))?;
+            return Ok(amount);
         }
-        Ok(())
+        let given = self.data.inventory.give(&definition, amount);
+        if given == 0 {
+            return Ok(0);
+        }
+        let mut buffer = packets::Buffer::default();
+        if given > 0 {
+            buffer.text = format!("You gained {} {}", given, definition.name);
+        } else {
+            buffer.text = format!("You lost {} {}", given.abs(), definition.name);
+        }
+        ctx.outbox
+            .send(self.addr, packets::packet::Data::Buffer(buffer));
+        Ok(given)
     }
-    pub fn set_weapon_data(&mut self) -> anyhow::Result<()> {
-        if self.data.weapon == "pistol" {
-            self.reload_time = 3500;
-            self.fire_time = 370;
-            self.automatic = false;
-        } else if self.data.weapon == "machinegun" {
-            self.reload_time = 1800;
-            self.fire_time = 50;
-            self.automatic = true;
-        } else if self.data.weapon == "grenade_launcher" {
-            self.reload_time = 2800;
-            self.fire_time = 800;
-            self.automatic = false;
+    pub fn set_weapon_data(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+        if let Some(weapon) = ctx.weapon_registry.get(&self.data.weapon) {
+            self.reload_time = weapon.reload_time.into();
+            self.fire_time = weapon.fire_time.into();
+            self.automatic = weapon.automatic;
         }
         let mut weapon_data = packets::WeaponData::default();
         weapon_data.fire_time = self.fire_time.try_into()?;
         weapon_data.reload_time = self.reload_time.try_into()?;
         weapon_data.automatic = self.automatic;
-        get_server().send(self.addr, packets::packet::Data::WeaponData(weapon_data))?;
+        ctx.outbox
+            .send(self.addr, packets::packet::Data::WeaponData(weapon_data));
         Ok(())
     }
-    pub fn draw(&mut self, weapon: String) -> anyhow::Result<()> {
-        if self.reloading {
+    pub fn weapon_sound(&self, registry: &WeaponRegistry, sound: &str) -> String {
+        match registry.get(&self.data.weapon) {
+            Some(weapon) => weapon.sound(sound),
+            None => format!("weapons/{}/{}.mp3", self.data.weapon, sound),
+        }
+    }
+    pub fn draw(&mut self, ctx: &mut Context, weapon: String) -> anyhow::Result<()> {
+        if self.reloading || !ctx.weapon_registry.contains(&weapon) {
             return Ok(());
         }
         self.firing = false;
-        self.play(format!("weapons/{}/draw.mp3", weapon))?;
-        self.say(weapon.clone())?;
+        self.say(ctx, weapon.clone())?;
         self.data.weapon = weapon;
-        self.set_weapon_data()?;
+        self.play(ctx, self.weapon_sound(&ctx.weapon_registry, "draw"))?;
+        self.set_weapon_data(ctx)?;
+        Ok(())
+    }
+    /// Plays a random shot sound and sends a projectile the way the player faces.
+    fn shoot(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+        let shot = ctx.rng.gen_range(1..=3).to_string();
+        self.play(ctx, self.weapon_sound(&ctx.weapon_registry, &shot))?;
+        self.data.take_ammo(self.data.weapon.clone(), -1);
+        let mut weapon = Weapon::new(
+            ctx,
+            self.data.x,
+            self.data.y,
+            self.data.direction,
+            self.data.weapon.clone(),
+            self.name.clone(),
+            self.data.map.clone(),
+        );
+        weapon.boost(self.buff_percent(BuffKind::Damage));
+        ctx.weapons.push(weapon);
         Ok(())
     }
-    pub fn fire(&mut self) -> anyhow::Result<()> {
+    pub fn fire(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
         if self.reloading
             || self.data.weapon == ""
-            || self.fire_timer.elapsed().as_millis() < self.fire_time
+            || ctx.now() - self.fire_timer < self.fire_time
             || self.data.safe
         {
             return Ok(());
         }
-        self.fire_timer = Instant::now();
+        self.fire_timer = ctx.now();
         if self.data.ammo.get(&self.data.weapon).is_none() {
-            self.play(format!("weapons/{}/empty.mp3", self.data.weapon))?;
+            self.play(ctx, self.weapon_sound(&ctx.weapon_registry, "empty"))?;
             return Ok(());
         }
         if self.automatic {
             self.firing = true;
         }
-        self.play(format!(
-            "weapons/{}/{}.mp3",
-            self.data.weapon,
-            get_server().rng.gen_range(1..=3)
-        ))?;
-        self.data.take_ammo(self.data.weapon.clone(), -1);
-        let weapon = Weapon::new(
-            self.data.x,
-            self.data.y,
-            self.data.direction,
-            self.data.weapon.clone(),
-            self.name.clone(),
-            self.data.map.clone(),
-        );
-        get_server().weapons.push(weapon);
-        Ok(())
+        self.shoot(ctx)
     }
     pub fn stop_fire(&mut self) {
         if self.automatic {
             self.firing = false;
         }
     }
-    pub fn reload(&mut self) -> anyhow::Result<()> {
+    pub fn reload(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
         if self.reloading || self.data.weapon.is_empty() {
             return Ok(());
         }
@@ -337,28 +453,139 @@ impl Player {
             return Ok(());
         }
         if self.data.cartridges.get(&self.data.weapon).is_none() {
-            self.say("You don't have any cartridges left!".to_string())?;
+            self.say(ctx, "You don't have any cartridges left!".to_string())?;
             return Ok(());
         }
         self.reloading = true;
-        self.reload_timer = Instant::now();
-        self.play(format!("weapons/{}/reload.mp3", self.data.weapon))?;
+        self.reload_timer = ctx.now();
+        self.play(ctx, self.weapon_sound(&ctx.weapon_registry, "reload"))?;
         self.data.take_cartridge(self.data.weapon.clone(), -1);
         Ok(())
     }
-    pub fn say(&self, text: String) -> anyhow::Result<()> {
-        let mut say = packets::Say::default();
-        say.text = text;
-        get_server().send(self.addr, packets::packet::Data::Say(say))?;
+    pub fn say(&self, ctx: &mut Context, text: String) -> anyhow::Result<()> {
+        ctx.outbox.say(self.addr, text);
         Ok(())
     }
-    pub fn change_map(&mut self, x: isize, y: isize, map: String) -> anyhow::Result<()> {
+    /// Lints a map this player is about to save as `name` and tells them about every problem.
+    /// Returns false if the map has errors and must not be saved.
+    pub fn check_map(&self, ctx: &mut Context, name: &str, data: &str) -> anyhow::Result<bool> {
+        let paths = &ctx.config.paths;
+        let problems = maps::check_map(&paths.maps, paths.client.as_deref(), name, data)?;
+        for problem in &problems {
+            self.say(ctx, problem.to_string())?;
+        }
+        if lint::has_errors(&problems) {
+            self.say(ctx, "The map was not saved".to_string())?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+    /// Sends the player back to where the server last saw them.
+    pub fn reject_move(&mut self, ctx: &mut Context, violation: Violation) -> anyhow::Result<()> {
+        let move_client = packets::MoveClient {
+            x: Some(self.data.x.try_into()?),
+            y: Some(self.data.y.try_into()?),
+            direction: Some(self.data.direction.try_into()?),
+            map: self.data.map.clone(),
+            who: self.name.clone(),
+        };
+        ctx.outbox
+            .send(self.addr, packets::packet::Data::MoveClient(move_client));
+        self.movement.reset();
+        if self.movement.violation(ctx.now()) {
+            ctx.outbox.admin_tell(format!(
+                "Alert: {} keeps making invalid moves, Last one: {} at {}, {} on {}",
+                self.name, violation, self.data.x, self.data.y, self.data.map
+            ));
+        }
+        Ok(())
+    }
+    /// Runs a chat message through the filter, returns what should be sent.
+    /// Blocked messages are reported to the admins and mute the player once
+    /// there were enough of them.
+    pub fn filter_chat(
+        &mut self,
+        ctx: &mut Context,
+        message: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let now = ctx.now();
+        let blocked = match self
+            .chat
+            .check(&ctx.config.chat, &ctx.deny_list, now, message)
+        {
+            Ok(message) => return Ok(Some(message)),
+            Err(blocked) => blocked,
+        };
+        // The message itself isn't passed on, it could crash the admins' speech too.
+        ctx.outbox.admin_tell(format!(
+            "Alert: A message from {} was blocked, {}",
+            self.name, blocked
+        ));
+        if !self.chat.violation(&ctx.config.chat) {
+            self.say(
+                ctx,
+                format!(
+                    "{}, Your message was not sent, Keep this up and you will be muted",
+                    blocked
+                ),
+            )?;
+            return Ok(None);
+        }
+        let time = ctx.config.chat.mute_time();
+        self.data.mute = Some(Mute::new("the server", "Spamming").lasting(time));
+        ctx.outbox.notify(format!(
+            "{} Have been muted by the server For {}, Reason: Spamming",
+            self.name,
+            format_time(time.into())
+        ));
+        Ok(None)
+    }
+    /// Takes the teleporter at the player's position, if there is one.
+    pub fn use_teleporter(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+        let teleporter = match ctx.maps.get(&self.data.map) {
+            Some(map) => match map.get_teleporter(self.data.x, self.data.y) {
+                Some(teleporter) => teleporter.clone(),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        if !ctx.maps.contains_key(&teleporter.map) {
+            self.say(
+                ctx,
+                format!(
+                    "Error: This teleporter leads to {} which doesn't exist",
+                    teleporter.map
+                ),
+            )?;
+            return Ok(());
+        }
+        let x = destination(&mut ctx.rng, &teleporter.x);
+        let y = destination(&mut ctx.rng, &teleporter.y);
+        self.change_map(ctx, x, y, teleporter.map)
+    }
+    pub fn change_map(
+        &mut self,
+        ctx: &mut Context,
+        x: isize,
+        y: isize,
+        map: String,
+    ) -> anyhow::Result<()> {
+        let max_x = match ctx.maps.get(&map) {
+            Some(grid) => grid.max_x as isize,
+            None => anyhow::bail!("The map {} doesn't exist", map),
+        };
+        if x < 0 || x > max_x || y < 0 {
+            anyhow::bail!("{}, {} is outside of {}", x, y, map);
+        }
+        let old_map = self.data.map.clone();
+        self.movement.reset();
         if map != self.data.map {
             self.data.map = map.clone();
             let parse = packets::ParseMap {
-                data: std::fs::read_to_string(format!("maps/{}.map", self.data.map.clone()))?,
+                data: std::fs::read_to_string(ctx.config.map_path(&self.data.map))?,
             };
-            get_server().send(self.addr, packets::packet::Data::ParseMap(parse))?;
+            ctx.outbox
+                .send(self.addr, packets::packet::Data::ParseMap(parse));
         }
         self.data.x = x;
         self.data.y = y;
@@ -367,87 +594,106 @@ impl Player {
         move_client.y = Some(y.try_into()?);
         move_client.direction = Some(self.data.direction.try_into()?);
         move_client.map = map.clone();
-        get_server().send(self.addr, packets::packet::Data::MoveClient(move_client))?;
+        ctx.outbox
+            .send(self.addr, packets::packet::Data::MoveClient(move_client));
         let mut move_packet = packets::Move::default();
         move_packet.x = Some(x.try_into()?);
         move_packet.y = Some(y.try_into()?);
         move_packet.direction = Some(self.data.direction.try_into()?);
         move_packet.who = self.name.clone();
         move_packet.map = map.clone();
-        for (_, i) in &mut get_server().players {
-            get_server().send(i.addr, packets::packet::Data::Move(move_packet.clone()))?;
+        // Players on the old map need to know this player left it, and players on the new
+        // one that they arrived.
+        if old_map != map {
+            ctx.outbox
+                .broadcast_map(packets::packet::Data::Move(move_packet.clone()), &old_map);
+        }
+        ctx.outbox
+            .broadcast_map(packets::packet::Data::Move(move_packet), &map);
+        if old_map != map {
+            // Movement on other maps isn't sent, so catch this player up with the players on the new one.
+            ctx.outbox.positions(self.addr);
         }
         Ok(())
     }
-    pub fn update(&mut self) -> anyhow::Result<()> {
-        if self.got_hit && self.cheat_timer.elapsed().as_millis() >= self.cheat_time {
+    /// Returns the name of whoever killed the player if they died.
+    pub fn update(&mut self, ctx: &mut Context) -> anyhow::Result<Option<String>> {
+        let now = ctx.now();
+        let mut expired = vec![];
+        self.buffs.retain(|b| {
+            if now - b.timer >= b.duration {
+                expired.push(b.name.clone());
+                return false;
+            }
+            true
+        });
+        for name in expired {
+            self.say(ctx, format!("{} wore off", name))?;
+        }
+        if self
+            .data
+            .mute
+            .as_ref()
+            .is_some_and(|m| m.expires.expired(Utc::now()))
+        {
+            self.data.mute = None;
+            self.say(ctx, String::from("Your mute has ended, You can chat again"))?;
+            ctx.outbox
+                .admin_tell(format!("{}'s mute has ended", self.name));
+        }
+        if self.got_hit && now - self.cheat_timer >= self.cheat_time {
             self.got_hit = false;
-            self.cheat_timer = Instant::now();
+            self.cheat_timer = now;
         }
+        let mut killer = None;
         if self.data.health <= 0 {
-            self.play(format!("player/death.mp3"))?;
+            self.play(ctx, String::from("player/death.mp3"))?;
             self.data.health = default_health();
             self.got_hit = false;
-            self.change_map(
-                get_server().rng.gen_range(0..=50),
-                0,
-                "safe_zone".to_string(),
-            )?;
+            let width = ctx.maps.get("safe_zone").map_or(0, |m| m.max_x as isize);
+            let x = ctx.rng.gen_range(0..=width.min(50));
+            self.change_map(ctx, x, 0, "safe_zone".to_string())?;
             let mut buf = packets::Buffer::default();
             buf.text = format!("{} Has been killed by {}", self.name, self.data.last_hit);
             buf.name = "kills".to_string();
-            get_server().broadcast(packets::packet::Data::Buffer(buf))?;
+            ctx.outbox.broadcast(packets::packet::Data::Buffer(buf));
             self.data.deaths += 1;
-            if let Some(player) = get_server().get_player_by_name(&self.data.last_hit) {
-                player.data.kills += 1;
-            }
+            killer = Some(self.data.last_hit.clone());
         }
-        if let Some(map) = get_server().get_map(&self.data.map) {
-            if !self.data.safe && map.get_safe_zone(self.data.x, self.data.y) {
+        if let Some(map) = ctx.maps.get(&self.data.map) {
+            let safe = map.get_safe_zone(self.data.x, self.data.y);
+            if !self.data.safe && safe {
                 self.data.safe = true;
-                self.play("player/safe.mp3".to_string())?;
+                self.play(ctx, "player/safe.mp3".to_string())?;
             }
-            if self.data.safe && !map.get_safe_zone(self.data.x, self.data.y) {
+            if self.data.safe && !safe {
                 self.data.safe = false;
-                self.play("player/unsafe.mp3".to_string())?;
+                self.play(ctx, "player/unsafe.mp3".to_string())?;
             }
         }
-        if self.reloading && self.reload_timer.elapsed().as_millis() >= self.reload_time {
+        if self.reloading && now - self.reload_timer >= self.reload_time {
             self.reloading = false;
-            self.reload_timer = Instant::now();
-            self.data
-                .take_ammo(self.data.weapon.clone(), self.data.get_weapon_total_ammo());
+            self.reload_timer = now;
+            self.data.take_ammo(
+                self.data.weapon.clone(),
+                self.data.get_weapon_total_ammo(&ctx.weapon_registry),
+            );
         }
-        if !self.data.safe && self.firing && self.fire_timer.elapsed().as_millis() >= self.fire_time
-        {
-            self.fire_timer = Instant::now();
+        if !self.data.safe && self.firing && now - self.fire_timer >= self.fire_time {
+            self.fire_timer = now;
             if self.data.ammo.get(&self.data.weapon).is_none() {
-                self.play(format!("weapons/{}/empty.mp3", self.data.weapon))?;
-                return Ok(());
+                self.play(ctx, self.weapon_sound(&ctx.weapon_registry, "empty"))?;
+                return Ok(killer);
             }
-            self.play(format!(
-                "weapons/{}/{}.mp3",
-                self.data.weapon,
-                get_server().rng.gen_range(1..=3)
-            ))?;
-            self.data.take_ammo(self.data.weapon.clone(), -1);
-            let weapon = Weapon::new(
-                self.data.x,
-                self.data.y,
-                self.data.direction,
-                self.data.weapon.clone(),
-                self.name.clone(),
-                self.data.map.clone(),
-            );
-            get_server().weapons.push(weapon);
+            self.shoot(ctx)?;
         }
-        Ok(())
+        Ok(killer)
     }
 
-    pub fn admin(&self) -> bool {
-        self.data.admin || self.data.dev
+    pub fn has_permission(&self, config: &ServerConfig, permission: &str) -> bool {
+        config.allows(&self.name, &self.data.roles, permission)
     }
-    pub fn play(&self, file: String) -> anyhow::Result<()> {
+    pub fn play(&self, ctx: &mut Context, file: String) -> anyhow::Result<()> {
         let mut play = packets::Play::default();
         play.x = Some(self.data.x.try_into()?);
         play.y = Some(self.data.y.try_into()?);
@@ -455,110 +701,108 @@ impl Player {
         play.map = self.data.map.clone();
         play.sound = file;
         play.self_play = Some(true);
-        get_server().broadcast(packets::packet::Data::Play(play))?;
+        ctx.outbox.broadcast_sound(
+            packets::packet::Data::Play(play),
+            &self.data.map,
+            self.data.x,
+            self.data.y,
+        );
         Ok(())
     }
-    pub fn self_play(&self, file: String) -> anyhow::Result<()> {
+    pub fn self_play(&self, ctx: &mut Context, file: String) -> anyhow::Result<()> {
         let mut play = packets::Play::default();
         play.who = self.name.clone();
         play.map = self.data.map.clone();
         play.sound = file;
         play.self_play = Some(true);
-        get_server().send(self.addr, packets::packet::Data::Play(play))?;
+        ctx.outbox
+            .send(self.addr, packets::packet::Data::Play(play));
         Ok(())
     }
 
-    pub fn check_account(user: String, password: String) -> anyhow::Result<bool> {
-        let formatted = format!("players/{}", user);
-        let player_dir = Path::new(&formatted);
-        if !player_dir.exists() {
-            return Ok(false);
+    pub fn check_account(ctx: &Context, user: String, password: String) -> anyhow::Result<bool> {
+        let storage = &ctx.storage;
+        let mut account = match storage.load_account(&user)? {
+            Some(account) => account,
+            None => return Ok(false),
+        };
+        if let Some(hash) = &account.password_hash {
+            return password::verify(&password, hash);
         }
-        let path = format!("players/{}/info.player", user);
-        let player_path = Path::new(&path);
-        if !player_path.exists() {
+        // An account with no password at all is damaged, nobody can log into it.
+        let Some(pw) = &account.password else {
+            return Ok(false);
+        };
+        if password != *pw {
             return Ok(false);
         }
-        let mut db = Dm::new(
-            path,
-            "jHYkfcq1UsvB15m7BMMlUNMNsOUlfeu-3AemrocWEJQ=".to_string(),
-        );
-        db.load();
-        if db.exists(String::from("password")) {
-            let pw = db.get("password");
-            if password != pw {
-                return Ok(false);
-            }
-        }
+        // Accounts from before passwords were hashed are upgraded on their next login.
+        password::upgrade(&mut account)?;
+        storage.save_account(&user, &account)?;
         Ok(true)
     }
     pub fn create(
+        ctx: &Context,
         addr: Peer,
         user: String,
         password: String,
         mail: String,
     ) -> anyhow::Result<bool> {
-        let formatted = format!("players/{}", user);
-        let player_dir = Path::new(&formatted);
-        if player_dir.exists() {
+        let storage = &ctx.storage;
+        if storage.account_exists(&user)? {
             return Ok(false);
         }
-        fs::create_dir_all(player_dir)?;
-        let path = format!("players/{}/info.player", user);
-        let mut db = Dm::new(
-            path,
-            "jHYkfcq1UsvB15m7BMMlUNMNsOUlfeu-3AemrocWEJQ=".to_string(),
-        );
-        db.add(String::from("password"), password);
-        db.add(String::from("mail"), mail);
-        db.save()?;
-        let player = Player::new(user, addr);
-        player.save()?;
+        let account = Account {
+            password_hash: Some(password::hash(&password)?),
+            password: None,
+            mail,
+        };
+        storage.save_account(&user, &account)?;
+        let player = Player::new(user, addr, ctx);
+        player.save(ctx)?;
         Ok(true)
     }
-    pub fn load(user: String, addr: Peer) -> anyhow::Result<Option<Player>> {
-        let formatted = format!("players/{}", user);
-        let player_dir = Path::new(&formatted);
-        if !player_dir.exists() {
-            return Ok(None);
-        }
-        let path = format!("players/{}/data.player", user);
-        let player_path = Path::new(&path);
-        if !player_path.exists() {
-            return Ok(None);
-        }
-        let f = match Fernet::new("gcj57_LgWAe8KnRUqGZLf7RnfxQWs7ZzPeAkCLHZh5M=") {
-            Some(f) => f,
+    pub fn load(ctx: &Context, user: String, addr: Peer) -> anyhow::Result<Option<Player>> {
+        let text = match ctx.storage.load_player(&user)? {
+            Some(text) => text,
             None => return Ok(None),
         };
-        let mut file = fs::File::open(player_path)?;
-        let mut text = String::new();
-        file.read_to_string(&mut text)?;
-        text = String::from_utf8(f.decrypt(&text)?)?;
-        let mut player = Player::new(user, addr);
-        player.data = serde_json::from_str(&text)?;
-        Ok(Some(player))
-    }
-    pub fn save(&self) -> anyhow::Result<()> {
-        let formatted = format!("players/{}", self.name);
-        let player_dir = Path::new(&formatted);
-        if !player_dir.exists() {
-            println!("Atemppted saving without a file existing");
-            //fs::create_dir(player_dir)?;
-            return Ok(());
+        let mut data: serde_json::Value = serde_json::from_str(&text)?;
+        if let Some(fields) = data.as_object_mut() {
+            // Saves without a loadout start with the one from weapons.toml.
+            let registry = &ctx.weapon_registry;
+            for (key, default) in [
+                ("weapons", registry.starting_weapons()),
+                ("ammo", registry.starting_ammo()),
+                ("cartridges", registry.starting_cartridges()),
+            ] {
+                if !fields.contains_key(key) {
+                    fields.insert(key.to_string(), serde_json::to_value(default)?);
+                }
+            }
         }
-        let path = format!("players/{}/data.player", self.name);
-        let player_path = Path::new(&path);
-        if !player_path.exists() {
-            fs::File::create(player_path)?;
+        let mut player = Player::new(user, addr, ctx);
+        player.data = serde_json::from_value(data)?;
+        if player.data.admin {
+            player.data.admin = false;
+            if !player.data.roles.iter().any(|r| r == "admin") {
+                player.data.roles.push(String::from("admin"));
+            }
         }
-        let f = match Fernet::new("gcj57_LgWAe8KnRUqGZLf7RnfxQWs7ZzPeAkCLHZh5M=") {
-            Some(f) => f,
-            None => return Ok(()),
-        };
+        if !player.data.can_chat {
+            player.data.can_chat = true;
+            player.data.mute = Some(Mute::new("unknown", ""));
+        }
+        // The map may have been deleted while the player was offline.
+        if !ctx.maps.contains_key(&player.data.map) {
+            player.data.map = String::from("main");
+            player.data.x = 0;
+            player.data.y = 0;
+        }
+        Ok(Some(player))
+    }
+    pub fn save(&self, ctx: &Context) -> anyhow::Result<()> {
         let data = serde_json::to_string(&self.data)?;
-        let text = f.encrypt(data.as_bytes());
-        fs::write(player_path, text)?;
-        Ok(())
+        ctx.storage.save_player(&self.name, &data)
     }
 }
@@ -64,7 +64,7 @@ pub fn format_time(input: u128) -> String {
         if i == length - 1 && length > 1 {
             result += " and ";
             result += unit;
-        } else if i == length - 2 || length == 1 {
+        } else if length == 1 || i == length - 2 {
             result += unit;
         } else {
             result += unit;
@@ -74,3 +74,34 @@ pub fn format_time(input: u128) -> String {
 
     result
 }
+
+/// Reads a duration such as `90s`, `30m`, `1h30m` or `2d` into milliseconds,
+/// a number without a unit is minutes.
+pub fn parse_time(input: &str) -> Option<u64> {
+    if let Ok(minutes) = input.parse::<u64>() {
+        return minutes.checked_mul(1000 * 60);
+    }
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit: u64 = match c {
+            's' => 1000,
+            'm' => 1000 * 60,
+            'h' => 1000 * 60 * 60,
+            'd' => 1000 * 60 * 60 * 24,
+            'w' => 1000 * 60 * 60 * 24 * 7,
+            _ => return None,
+        };
+        let value = number.parse::<u64>().ok()?;
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        number.clear();
+    }
+    if !number.is_empty() || total == 0 {
+        return None;
+    }
+    Some(total)
+}
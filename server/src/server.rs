@@ -1,693 +1,361 @@
 use crate::{
-    bans, connection::Connection, dm::Dm, maps, maps::grid::Grid, player::Player,
-    readable_time::format_time, weapon::Weapon,
+    bans::{self, Ban},
+    chat_filter::DenyList,
+    commands::{Call, COMMANDS},
+    config::{LimitAction, ServerConfig},
+    connection::{self, Connection, Spent},
+    dm::Dm,
+    item_registry::ItemRegistry,
+    keys::Keys,
+    maps,
+    maps::grid::Grid,
+    outbox::{Audience, Outbox, Outgoing},
+    player::{self, Player, Players},
+    readable_time::format_time,
+    storage,
+    storage::Storage,
+    weapon::Weapon,
+    weapon_registry::WeaponRegistry,
 };
+use chrono::Utc;
+use coas_transport::{Handshake, Role};
 use enet::*;
-use fernet::Fernet;
-use once_cell::sync::Lazy;
+use once_cell::sync::OnceCell;
 use prost::Message;
 use rand::{rngs::ThreadRng, Rng};
 use semver::Version;
 use serde_derive::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, io::Read, path::Path, sync::Mutex, time::Instant};
+use std::{
+    collections::HashMap,
+    fs,
+    time::{Duration, Instant},
+};
 pub mod packets {
     include!(concat!(env!("OUT_DIR"), "/network.packets.rs"));
 }
 use packets::packet::Data;
 
-const RULES: &str = include_str!("rules.txt");
+pub const RULES: &str = include_str!("rules.txt");
+
+/// How many times a second the game advances.
+pub const TICK_RATE: u32 = 50;
+pub const TICK: Duration = Duration::from_millis(1000 / TICK_RATE as u64);
 
-pub static mut SERVER: Lazy<Mutex<Server>> = Lazy::new(|| {
-    let mut server = Mutex::new(Server::new(18832).unwrap());
-    server.get_mut().unwrap().load().unwrap();
-    server
-});
+static ENET: OnceCell<Enet> = OnceCell::new();
 
-pub fn get_server() -> &'static mut Server {
-    unsafe { SERVER.get_mut().unwrap() }
+/// ENet can only be initialized once per process, every host shares this handle.
+pub fn enet() -> anyhow::Result<Enet> {
+    Ok(ENET.get_or_try_init(Enet::new)?.clone())
 }
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct ServerData {
     peak: usize,
     #[serde(default)]
-    bans: bans::Bans,
+    pub bans: bans::Bans,
 }
-pub struct Server {
-    #[allow(dead_code)]
-    enet: Enet,
-    host: Host<()>,
-    #[allow(dead_code)]
-    addr: Address,
+
+/// The game state apart from the network and the players, handed to
+/// everything that acts on players so they can be borrowed separately.
+pub struct Context {
     up_timer: Instant,
     pub data: ServerData,
     pub rng: ThreadRng,
-    pub players: HashMap<Peer, Player>,
     pub weapons: Vec<Weapon>,
-    foo: Dm,
-    pub connections: HashMap<Peer, Connection>,
+    pub weapon_registry: WeaponRegistry,
+    pub item_registry: ItemRegistry,
     pub maps: HashMap<String, Grid>,
+    pub storage: Box<dyn Storage>,
+    pub config: ServerConfig,
+    /// Compiled from `config.chat` when the server starts.
+    pub deny_list: DenyList,
+    pub outbox: Outbox,
+    /// Set to shut the server down once the current tick is done.
+    pub stopping: bool,
+    /// How many ticks have run since the server started.
+    pub tick: u64,
 }
 
-impl Server {
-    pub fn new(port: u16) -> anyhow::Result<Self> {
-        let enet = Enet::new()?;
-        let addr = Address::new(std::net::Ipv4Addr::UNSPECIFIED, port);
-        let host = enet.create_host::<()>(
-            Some(&addr),
-            100,
-            ChannelLimit::Maximum,
-            BandwidthLimit::Unlimited,
-            BandwidthLimit::Unlimited,
-        )?;
-        let maps = maps::parse_all_maps()?;
+impl Context {
+    pub fn new(config: ServerConfig, keys: &Keys) -> anyhow::Result<Self> {
+        let maps = maps::parse_all_maps(&config.paths.maps, config.paths.client.as_deref())?;
+        let storage = storage::open(&config.paths.storage, &config.paths.data, keys)?;
+        let weapon_registry = WeaponRegistry::load(&config.paths.weapons)?;
+        let item_registry = ItemRegistry::load(&config.paths.items, &weapon_registry)?;
+        for map in maps.values() {
+            map.check_items(&item_registry)?;
+        }
+        let deny_list = DenyList::new(&config.chat)?;
         Ok(Self {
-            enet,
-            host,
-            addr,
             up_timer: Instant::now(),
             data: ServerData::default(),
             rng: rand::thread_rng(),
             weapons: vec![],
-            foo: Dm::new(
-                String::new(),
-                String::from("KcBkgRw_ju2TbjHc9V21VY9-bm0U2mRAKPZdM9aKZ_E="),
-            ),
-            players: HashMap::new(),
-            connections: HashMap::new(),
+            weapon_registry,
+            item_registry,
             maps,
+            storage,
+            config,
+            deny_list,
+            outbox: Outbox::default(),
+            stopping: false,
+            tick: 0,
         })
     }
+    /// The game time in milliseconds, it only moves when the server ticks so
+    /// game timers measured with it are independent of the machine's speed.
+    pub fn now(&self) -> u128 {
+        self.tick as u128 * TICK.as_millis()
+    }
     pub fn load(&mut self) -> anyhow::Result<()> {
-        let data_file = Path::new("server.dat");
-        if !data_file.exists() {
-            return Ok(());
+        if let Some(text) = self.storage.load_server()? {
+            self.data = serde_json::from_str(&text)?;
+            self.data.bans.migrate();
         }
-        let f = match Fernet::new("gcj57_LgWAe8KnRUqGZLf7RnfxQWs7ZzPeAkCLHZh5M=") {
-            Some(f) => f,
-            None => return Ok(()),
-        };
-        let mut file = fs::File::open(data_file)?;
-        let mut text = String::new();
-        file.read_to_string(&mut text)?;
-        text = String::from_utf8(f.decrypt(&text)?)?;
-        self.data = serde_json::from_str(&text)?;
         Ok(())
     }
     pub fn save(&self) -> anyhow::Result<()> {
-        let data_file = Path::new("server.dat");
-        let f = match Fernet::new("gcj57_LgWAe8KnRUqGZLf7RnfxQWs7ZzPeAkCLHZh5M=") {
-            Some(f) => f,
-            None => return Ok(()),
-        };
         let data = serde_json::to_string(&self.data)?;
-        let text = f.encrypt(data.as_bytes());
-        fs::write(data_file, text)?;
-        Ok(())
+        self.storage.save_server(&data)
     }
-    pub fn get_player(&mut self, peer: &Peer) -> Option<&mut Player> {
-        self.players.get_mut(peer)
+}
+
+/// A packet ready to be sent, Fernet peers share one token.
+struct Frame {
+    bytes: Vec<u8>,
+    token: Option<String>,
+}
+
+pub struct Server {
+    #[allow(dead_code)]
+    enet: Enet,
+    host: Host<()>,
+    #[allow(dead_code)]
+    addr: Address,
+    /// Encrypts the packets of clients that don't do the session handshake.
+    fernet: Dm,
+    pub connections: HashMap<Peer, Connection>,
+    pub players: Players,
+    pub ctx: Context,
+    /// Packets that couldn't be read or handled since the server started.
+    pub packet_errors: u64,
+}
+
+fn bandwidth_limit(bytes: u32) -> BandwidthLimit {
+    match bytes {
+        0 => BandwidthLimit::Unlimited,
+        bytes => BandwidthLimit::Limited(bytes),
     }
-    pub fn get_player_by_name(&mut self, name: &str) -> Option<&mut Player> {
-        if let Some((_, player)) = self
-            .players
-            .iter_mut()
-            .find(|(_, i)| i.name.to_lowercase() == name.to_lowercase())
-        {
-            return Some(player);
-        }
-        None
+}
+
+impl Server {
+    pub fn new(config: ServerConfig) -> anyhow::Result<Self> {
+        let keys = Keys::load(&config.paths.keys)?;
+        Self::with_keys(config, keys)
     }
-    pub fn get_map(&mut self, name: &str) -> Option<&mut Grid> {
-        self.maps.get_mut(name)
+    pub fn with_keys(config: ServerConfig, keys: Keys) -> anyhow::Result<Self> {
+        let enet = enet()?;
+        let network = &config.network;
+        let addr = Address::new(network.bind, network.port);
+        let host = enet.create_host::<()>(
+            Some(&addr),
+            network.max_peers,
+            ChannelLimit::Maximum,
+            bandwidth_limit(network.incoming_bandwidth),
+            bandwidth_limit(network.outgoing_bandwidth),
+        )?;
+        Ok(Self {
+            enet,
+            host,
+            addr,
+            fernet: Dm::new(String::new(), keys.transport.clone()),
+            connections: HashMap::new(),
+            players: HashMap::new(),
+            ctx: Context::new(config, &keys)?,
+            packet_errors: 0,
+        })
     }
-    pub fn get_connection(&mut self, peer: Peer) -> Option<&mut Connection> {
-        self.connections.get_mut(&peer)
+    pub fn load(&mut self) -> anyhow::Result<()> {
+        self.ctx.load()
     }
-    pub fn play(&mut self, sound: &str, x: isize, y: isize, map: &str) -> anyhow::Result<()> {
-        let mut play = packets::Play::default();
-        play.x = Some(x.try_into()?);
-        play.y = Some(y.try_into()?);
-        play.map = map.to_string();
-        play.sound = sound.to_string();
-        let data = Data::Play(play);
-        for (_, i) in &mut self.players {
-            if i.data.map == map {
-                get_server().send(i.addr, data.clone())?;
-            }
-        }
-        Ok(())
+    /// Where the server listens, the real port when it was started on port 0.
+    pub fn address(&self) -> Address {
+        self.host.address()
     }
-    pub fn send(&mut self, mut peer: Peer, data: Data) -> anyhow::Result<()> {
-        let mut pac = packets::Packet::default();
-        pac.data = Some(data);
-        let mut buf = vec![];
-        pac.encode(&mut buf)?;
-        peer.send_packet(
-            Packet::new(
-                self.foo.encrypto(buf.as_slice()).as_bytes(),
-                PacketMode::ReliableSequenced,
-            )?,
-            0,
-        )?;
-        Ok(())
+    pub fn running(&self) -> bool {
+        !self.ctx.stopping
     }
-    pub fn broadcast(&mut self, data: Data) -> anyhow::Result<()> {
-        let mut pac = packets::Packet::default();
-        pac.data = Some(data);
-        let mut buf = vec![];
-        pac.encode(&mut buf)?;
-        for (peer, _) in self.players.iter() {
-            peer.clone().send_packet(
-                Packet::new(
-                    self.foo.encrypto(buf.as_slice()).as_bytes(),
-                    PacketMode::ReliableSequenced,
-                )?,
-                0,
-            )?;
-        }
+    /// Sends whatever is still queued before the server exits.
+    pub fn shutdown(&mut self) -> anyhow::Result<()> {
+        self.flush()?;
+        self.host.flush();
         Ok(())
     }
-    pub fn notify(&mut self, text: String) -> anyhow::Result<()> {
-        let mut note = packets::Buffer::default();
-        note.text = text;
-        note.name = String::from("notifications");
-        note.sound = String::from("notifications/alert.mp3");
-        self.broadcast(Data::Buffer(note))?;
-        Ok(())
+    fn encode(data: Data) -> Frame {
+        let packet = packets::Packet { data: Some(data) };
+        Frame {
+            bytes: packet.encode_to_vec(),
+            token: None,
+        }
     }
-    pub fn admin_tell(&mut self, text: String) -> anyhow::Result<()> {
-        let mut buf = packets::Buffer::default();
-        buf.text = text;
-        buf.name = String::from("admin alerts");
-        buf.sound = String::from("notifications/admin_tell.mp3");
-        for (_, i) in &self.players {
-            if i.admin() {
-                get_server().send(i.addr, Data::Buffer(buf.clone()))?;
+    /// Encrypts a packet for a peer with its session, or with Fernet if it has none.
+    fn send_frame(&mut self, peer: Peer, frame: &mut Frame) -> anyhow::Result<()> {
+        let session = self
+            .connections
+            .get_mut(&peer)
+            .and_then(|c| c.session.as_mut());
+        match session {
+            Some(session) => Self::send_bytes(peer, &session.seal(&frame.bytes)?),
+            None => {
+                let fernet = &mut self.fernet;
+                let token = frame
+                    .token
+                    .get_or_insert_with(|| fernet.encrypto(&frame.bytes));
+                Self::send_bytes(peer, token.as_bytes())
             }
         }
-        Ok(())
     }
-    pub fn process_command(&mut self, command: &str, peer: Peer) -> anyhow::Result<()> {
-        let parsed = command.split_whitespace().collect::<Vec<&str>>();
-        if parsed.is_empty() {
+    fn send_bytes(mut peer: Peer, bytes: &[u8]) -> anyhow::Result<()> {
+        // ENet refuses packets for peers that are being disconnected.
+        if peer.state() != PeerState::Connected {
             return Ok(());
         }
-        if parsed[0] == "rawmap" {
-            if let Some(player) = self.get_player(&peer) {
-                if !player.admin() {
-                    return Ok(());
-                }
-                let mut buf = packets::Buffer::default();
-                buf.text = std::fs::read_to_string(format!("maps/{}.map", player.data.map))?;
-                self.send(peer, Data::Buffer(buf))?;
-            }
-        } else if parsed[0] == "rawdata" && parsed.len() > 2 {
-            if let Some(player) = self.get_player(&peer) {
-                if !player.admin() {
-                    return Ok(());
-                }
-                std::fs::write(
-                    format!("maps/{}.map", player.data.map),
-                    command.replacen("rawdata ", "", 1),
-                )?;
-                get_server().maps.insert(
-                    player.data.map.clone(),
-                    maps::parse_map(&format!("maps/{}.map", player.data.map.clone()))?,
-                );
-                let parse = packets::ParseMap {
-                    data: std::fs::read_to_string(format!("maps/{}.map", player.data.map.clone()))?,
+        peer.send_packet(Packet::new(bytes, PacketMode::ReliableSequenced)?, 0)?;
+        Ok(())
+    }
+    /// Decrypts a packet and handles it. A peer's first packet decides how it
+    /// is encrypted, a hello starts a session and anything else is Fernet.
+    fn receive(&mut self, peer: Peer, bytes: &[u8]) -> anyhow::Result<()> {
+        let connection = match self.connections.get_mut(&peer) {
+            Some(connection) => connection,
+            None => anyhow::bail!("Packet from a peer that isn't connected"),
+        };
+        let data = match connection.session.as_mut() {
+            Some(session) => session.open(bytes)?,
+            None if coas_transport::is_hello(bytes) => {
+                let handshake = Handshake::new();
+                let hello = handshake.hello();
+                connection.session = Some(handshake.finish(bytes, Role::Server)?);
+                return Self::send_bytes(peer, &hello);
+            }
+            None if self.ctx.config.network.fernet_transport => self.fernet.decrypto(bytes)?,
+            None => {
+                let client = &self.ctx.config.client;
+                let error = packets::Error {
+                    reason: format!("This server needs a newer version of the game, Download it from {}, If you are running linux, Download from {}", client.download_url, client.linux_download_url),
                 };
-                for (_, i) in &get_server().players {
-                    if i.data.map == player.data.map {
-                        get_server().send(i.addr, Data::ParseMap(parse.clone()))?;
-                        get_server().send(
-                            i.addr,
-                            Data::Buffer(packets::Buffer {
-                                text: String::from("Map updated!"),
-                                name: String::new(),
-                                sound: String::new(),
-                            }),
-                        )?;
-                    }
-                }
-            }
-        } else if parsed[0] == "admin" && parsed.len() == 2 {
-            if let Some(player) = self.get_player(&peer) {
-                if player.data.dev {
-                    if let Some(handle) = get_server().get_player_by_name(parsed[1]) {
-                        if handle.data.admin {
-                            handle.data.admin = false;
-                            let mut buf = packets::Buffer::default();
-                            buf.text = String::from("You are no longer an admin");
-                            get_server().send(handle.addr, Data::Buffer(buf))?;
-                            get_server().notify(format!(
-                                "{} Has been removed from the administrator status",
-                                handle.name
-                            ))?;
-                        } else {
-                            handle.data.admin = true;
-                            let mut buf = packets::Buffer::default();
-                            buf.text = String::from("You are now an admin");
-                            get_server().send(handle.addr, Data::Buffer(buf))?;
-                            get_server().notify(format!(
-                                "{} Has been promoted to administrator",
-                                handle.name
-                            ))?;
-                        }
-                    }
-                }
-            }
-        } else if parsed[0] == "at" {
-            if let Some(player) = self.get_player(&peer) {
-                if player.admin() {
-                    get_server().admin_tell(format!(
-                        "Admin chat from {}: {}",
-                        player.name,
-                        command.replacen("at ", "", 1)
-                    ))?;
-                }
-            }
-        } else if parsed[0] == "admintell" {
-            if let Some(player) = self.get_player(&peer) {
-                get_server().admin_tell(format!(
-                    "Admin tell from {}: {}",
-                    player.name,
-                    command.replacen("admintell ", "", 1)
-                ))?;
-            }
-        } else if parsed[0] == "notify" && parsed.len() > 2 {
-            if let Some(player) = self.get_player(&peer) {
-                if player.admin() {
-                    get_server().notify(command.replacen("notify ", "", 1))?;
-                    get_server().admin_tell(format!(
-                        "{} Has just sent a notification to the server!",
-                        player.name
-                    ))?;
-                }
-            }
-        } else if parsed[0] == "me" && parsed.len() > 1 {
-            if let Some(player) = self.get_player(&peer) {
-                let mut buf = packets::Buffer::default();
-                buf.text = format!("{} {}", player.name, command.replacen("me ", "", 1));
-                buf.name = String::from("chat");
-                buf.sound = String::from("notifications/chat.mp3");
-                self.send(peer, Data::Buffer(buf))?;
-            }
-        } else if parsed[0] == "can_chat" && parsed.len() > 1 {
-            if let Some(player) = self.get_player(&peer) {
-                if !player.admin() {
-                    return Ok(());
-                }
-                if let Some(handle) = get_server().get_player_by_name(parsed[1]) {
-                    if handle.data.can_chat {
-                        handle.data.can_chat = false;
-                        get_server().notify(format!(
-                            "{}'s chats were disabled by {}",
-                            handle.name, player.name
-                        ))?;
-                    } else {
-                        handle.data.can_chat = true;
-                        get_server().notify(format!(
-                            "{}'s chats were enabled by {}",
-                            handle.name, player.name
-                        ))?;
-                    }
-                }
-            }
-        } else if parsed[0] == "rules" {
-            let mut buf = packets::Buffer::default();
-            buf.text = RULES.to_string();
-            self.send(peer, Data::Buffer(buf))?;
-        } else if parsed[0] == "agree" {
-            if let Some(player) = self.get_player(&peer) {
-                if !player.data.agreed_to_rules {
-                    player.data.agreed_to_rules = true;
-                    player.say(String::from(
-                        "Success, You have agreed to the rules, Welcome to the game.",
-                    ))?;
-                } else {
-                    player.say(String::from("You have already agreed to the rules!"))?;
-                }
-            }
-        } else if parsed[0] == "save" {
-            if let Some(player) = self.get_player(&peer) {
-                if player.admin() {
-                    for i in get_server().players.values_mut() {
-                        i.save()?;
-                        get_server().send(i.addr, Data::Close(packets::Close::default()))?;
-                    }
-                    println!("Server have been restarted by {}", player.name);
-                    self.save()?;
-                    std::process::exit(0);
-                }
-            }
-        } else if parsed[0] == "hit_ping" {
-            if let Some(player) = self.get_player(&peer) {
-                if player.data.hit_ping {
-                    player.data.hit_ping = false;
-                    player.say("Hitting ping turned off!".to_string())?;
-                } else {
-                    player.data.hit_ping = true;
-                    player.say("Hitting ping turned on!".to_string())?;
-                }
-            }
-        } else if parsed[0] == "kick" && parsed.len() == 2 {
-            if let Some(player) = get_server().get_player(&peer) {
-                if player.admin() {
-                    if let Some(handle) = self.get_player_by_name(parsed[1]) {
-                        get_server().notify(format!(
-                            "{} have been kicked by {}",
-                            handle.name, player.name
-                        ))?;
-                        get_server().send(handle.addr, Data::Close(packets::Close::default()))?;
-                        let mut off = packets::Offline::default();
-                        off.who = handle.name.clone();
-                        get_server().broadcast(Data::Offline(off))?;
-                        handle.save()?;
-                        get_server().players.remove(&handle.addr);
-                    }
-                }
-            }
-        } else if parsed[0] == "ban" && parsed.len() > 1 {
-            if let Some(player) = get_server().get_player(&peer) {
-                if !player.admin() {
-                    return Ok(());
-                }
-                if let Some(handle) = get_server().get_player_by_name(parsed[1]) {
-                    get_server()
-                        .data
-                        .bans
-                        .add_ban(handle.name.clone(), handle.data.id.clone());
-                    get_server().save()?;
-                    get_server().notify(format!(
-                        "{} Have been banned by {}",
-                        handle.name, player.name
-                    ))?;
-                    get_server().send(handle.addr, Data::Close(packets::Close::default()))?;
-                    let mut off = packets::Offline::default();
-                    off.who = handle.name.clone();
-                    get_server().broadcast(Data::Offline(off))?;
-                    handle.save()?;
-                    get_server().players.remove(&handle.addr);
-                } else {
-                    if Path::new(format!("players/{}", parsed[1]).as_str()).exists() {
-                        if let Some(peer) = self.host.peers().last() {
-                            if let Some(player2) = Player::load(parsed[1].to_string(), peer)? {
-                                if get_server().data.bans.get_user(player2.data.id.clone()) {
-                                    get_server().data.bans.remove_ban(player2.name.clone());
-                                    get_server().save()?;
-                                    get_server().notify(format!(
-                                        "{} Have been unbanned by {}",
-                                        player2.name, player.name
-                                    ))?;
-                                    return Ok(());
-                                }
-                                get_server()
-                                    .data
-                                    .bans
-                                    .add_ban(player2.name.clone(), player2.data.id);
-                                get_server().save()?;
-                                get_server().notify(format!(
-                                    "{} Have been banned by {}",
-                                    player2.name, player.name
-                                ))?;
-                            }
-                        }
-                    } else {
-                        player.say("Error, Player not found!".to_string())?;
-                        return Ok(());
-                    }
-                }
+                self.ctx.outbox.send(peer, Data::Error(error));
+                self.ctx.outbox.disconnect(peer);
+                return Ok(());
             }
-        } else if parsed[0] == "timed_ban" && parsed.len() > 2 {
-            if let Some(player) = get_server().get_player(&peer) {
-                if !player.admin() {
-                    return Ok(());
-                }
-                let time = match parsed[2].parse::<u64>() {
-                    Ok(time) => time,
-                    Err(e) => {
-                        player.say(format!("Error in second argument(Time), Reason: {}", e))?;
-                        return Ok(());
-                    }
-                };
-                if let Some(handle) = get_server().get_player_by_name(parsed[1]) {
-                    get_server().data.bans.add_temporary(
-                        handle.name.clone(),
-                        handle.data.id.clone(),
-                        time * 1000 * 60,
-                    );
-                    get_server().save()?;
-                    get_server().notify(format!(
-                        "{} Have been temporarily banned by {} For {}",
-                        handle.name,
-                        player.name,
-                        format_time((time * 1000 * 60).try_into()?)
-                    ))?;
-                    get_server().send(handle.addr, Data::Close(packets::Close::default()))?;
-                    let mut off = packets::Offline::default();
-                    off.who = handle.name.clone();
-                    get_server().broadcast(Data::Offline(off))?;
-                    handle.save()?;
-                    get_server().players.remove(&handle.addr);
-                } else {
-                    if Path::new(format!("players/{}", parsed[1]).as_str()).exists() {
-                        if let Some(peer) = self.host.peers().last() {
-                            if let Some(player2) = Player::load(parsed[1].to_string(), peer)? {
-                                if get_server()
-                                    .data
-                                    .bans
-                                    .get_temporary(player2.data.id.clone())
-                                {
-                                    get_server()
-                                        .data
-                                        .bans
-                                        .remove_temporary(player2.name.clone());
-                                    get_server().save()?;
-                                    get_server().notify(format!(
-                                        "{} Have been unbanned by {}",
-                                        player2.name, player.name
-                                    ))?;
-                                    return Ok(());
-                                }
-                                get_server().data.bans.add_temporary(
-                                    player2.name.clone(),
-                                    player2.data.id,
-                                    time * 1000 * 60,
-                                );
-                                get_server().save()?;
-                                get_server().notify(format!(
-                                    "{} Have been temporarily banned by {} For {}",
-                                    player2.name,
-                                    player.name,
-                                    format_time((time * 1000 * 60).try_into()?)
-                                ))?;
-                            }
-                        }
-                    } else {
-                        player.say("Error, Player not found!".to_string())?;
-                        return Ok(());
+        };
+        self.process_packet(&data, peer)
+    }
+    fn hears(&self, audience: &Audience, player: &Player) -> bool {
+        match audience {
+            Audience::Peer(peer) => player.addr == *peer,
+            Audience::All => true,
+            Audience::Map(map) => player.data.map == *map,
+            Audience::Sound { map, x, y } => {
+                let distance = self.ctx.config.hearing_distance;
+                player.data.map == *map
+                    && distance.is_none_or(|d| {
+                        (player.data.x - x).abs() <= d && (player.data.y - y).abs() <= d
+                    })
+            }
+            Audience::Permission(permission) => player.has_permission(&self.ctx.config, permission),
+        }
+    }
+    /// Sends everything queued in the outbox, each packet is encrypted only once.
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        for outgoing in self.ctx.outbox.take() {
+            match outgoing {
+                Outgoing::Packet(Audience::Peer(peer), data) => {
+                    self.send_frame(peer, &mut Self::encode(data))?;
+                }
+                Outgoing::Packet(audience, data) => {
+                    let mut frame = Self::encode(data);
+                    let peers: Vec<Peer> = self
+                        .players
+                        .values()
+                        .filter(|player| self.hears(&audience, player))
+                        .map(|player| player.addr)
+                        .collect();
+                    for peer in peers {
+                        self.send_frame(peer, &mut frame)?;
                     }
                 }
-            }
-        } else if parsed[0] == "move" && parsed.len() >= 4 {
-            if let Some(player) = self.get_player(&peer) {
-                if !player.admin() {
-                    return Ok(());
-                }
-                if let Some(handle) = get_server().get_player_by_name(parsed[1]) {
-                    let mut map = handle.data.map.clone();
-                    if parsed.len() == 5 {
-                        map = parsed[4].to_string();
-                    }
-                    if !get_server().maps.contains_key(&map) {
-                        player.say("That map does not exist!".to_string())?;
-                        return Ok(());
-                    }
-                    let x = match parsed[2].parse() {
-                        Ok(x) => x,
-                        Err(e) => {
-                            player.say(format!(
-                                "Error: Couldn't change the player's location, Reason: {}",
-                                e
-                            ))?;
-                            return Ok(());
-                        }
+                Outgoing::Positions(peer) => {
+                    let map = match self.players.get(&peer) {
+                        Some(player) => player.data.map.clone(),
+                        None => continue,
                     };
-                    let y = match parsed[3].parse() {
-                        Ok(y) => y,
-                        Err(e) => {
-                            player.say(format!(
-                                "Error: Couldn't change the player's location, Reason: {}",
-                                e
-                            ))?;
-                            return Ok(());
-                        }
-                    };
-                    match handle.change_map(x, y, map) {
-                        Ok(_) => (),
-                        Err(e) => {
-                            player.say(format!(
-                                "Error: Couldn't change the player's location, Reason: {}",
-                                e
-                            ))?;
+                    let mut moves = vec![];
+                    for i in self.players.values() {
+                        if i.addr == peer || i.data.map != map {
+                            continue;
                         }
+                        moves.push(packets::Move {
+                            x: Some(i.data.x.try_into()?),
+                            y: Some(i.data.y.try_into()?),
+                            direction: Some(i.data.direction.try_into()?),
+                            who: i.name.clone(),
+                            map: i.data.map.clone(),
+                            ..Default::default()
+                        });
                     }
-                }
-            }
-        } else if parsed[0] == "newmap" && parsed.len() >= 5 {
-            if let Some(player) = self.get_player(&peer) {
-                if !player.admin() {
-                    return Ok(());
-                }
-                if get_server().get_map(parsed[1]).is_some() {
-                    player.say("That map already exists!".to_string())?;
-                    return Ok(());
-                }
-                let text = format!(
-                    "map {}
-                maxx {}
-                maxy {}
-                tile 0 {} 0 0 {}",
-                    parsed[1], parsed[2], parsed[3], parsed[2], parsed[4]
-                );
-                match fs::write(format!("maps/{}.map", parsed[1]), text) {
-                    Ok(_) => {
-                        player.change_map(0, 0, parsed[1].to_string())?;
-                        get_server().admin_tell(format!(
-                            "{} Has created a new map: {}",
-                            player.name, parsed[1]
-                        ))?;
-                        self.maps.insert(
-                            parsed[1].to_string(),
-                            maps::parse_map(&format!("maps/{}.map", parsed[1]))?,
-                        );
-                    }
-                    Err(e) => {
-                        player.say(format!("Error: Unable to create the map, Reason: {}", e))?;
-                    }
-                }
-            }
-        } else if parsed[0] == "remmap" {
-            if let Some(player) = self.get_player(&peer) {
-                let mut map = player.data.map.clone();
-                if parsed.len() == 2 {
-                    map = parsed[1].to_string();
-                }
-                if map == "main" || map == "safe_zone" {
-                    player.say("Error: This map cannot be deleted".to_string())?;
-                    return Ok(());
-                }
-                let map_path = format!("maps/{}.map", map);
-                let path = Path::new(&map_path);
-                if !path.exists() {
-                    player.say("That map does not exist".to_string())?;
-                    return Ok(());
-                }
-                match fs::remove_file(path) {
-                    Ok(_) => {
-                        player.say("Success".to_string())?;
-                        get_server()
-                            .admin_tell(format!("{} Have deleted the map {}", player.name, map))?;
-                        player.change_map(0, 0, "main".to_string())?;
-                    }
-                    Err(e) => {
-                        player.say(format!("Error: Unable to delete the map, Reason: {}", e))?;
-                    }
-                }
-            }
-        } else if parsed[0] == "give" && parsed.len() >= 4 {
-            if let Some(player) = self.get_player(&peer) {
-                if !player.admin() {
-                    return Ok(());
-                }
-                if let Some(handle) = get_server().get_player_by_name(parsed[1]) {
-                    let amount = match parsed[3].parse() {
-                        Ok(amount) => amount,
-                        Err(e) => {
-                            player.say(format!("Error: Unable to give, Reason: {}", e))?;
-                            return Ok(());
-                        }
-                    };
-                    handle.give(parsed[2], amount)?;
-                    get_server().admin_tell(format!(
-                        "{} has just given {} {} {}s",
-                        player.name, handle.name, parsed[3], parsed[2]
-                    ))?;
-                }
-            }
-        } else if parsed[0] == "giveall" && parsed.len() >= 3 {
-            if let Some(player) = self.get_player(&peer) {
-                if !player.admin() {
-                    return Ok(());
-                }
-                let amount = match parsed[2].parse() {
-                    Ok(amount) => amount,
-                    Err(e) => {
-                        player.say(format!("Error: Unable to give, Reason: {}", e))?;
-                        return Ok(());
+                    for move_packet in moves {
+                        self.send_frame(peer, &mut Self::encode(Data::Move(move_packet)))?;
                     }
-                };
-                for (_, i) in &mut get_server().players {
-                    i.give(parsed[1], amount)?;
-                }
-                get_server().admin_tell(format!(
-                    "{} has just given everyone {} {}s",
-                    player.name, parsed[2], parsed[1]
-                ))?;
-            }
-        } else if parsed[0] == "note" && parsed.len() > 1 {
-            if let Some(player) = self.get_player(&peer) {
-                if player.admin() {
-                    let cmd = command.replacen("note ", "", 1);
-                    fs::write("note.txt", &cmd)?;
-                    get_server().notify(format!(
-                        "{} Have just changed the server note to {}",
-                        player.name, cmd
-                    ))?;
                 }
+                Outgoing::Disconnect(mut peer) => peer.disconnect_later(0),
             }
         }
         Ok(())
     }
+    pub fn process_command(&mut self, command: &str, peer: Peer) -> anyhow::Result<()> {
+        let name = match self.players.get(&peer) {
+            Some(player) => player.name.clone(),
+            None => return Ok(()),
+        };
+        let mut call = Call {
+            peer,
+            name,
+            players: &mut self.players,
+            ctx: &mut self.ctx,
+        };
+        COMMANDS.dispatch(&mut call, command)
+    }
     pub fn process_packet(&mut self, buf: &[u8], addr: Peer) -> anyhow::Result<()> {
-        let packet = packets::Packet::decode(buf);
-        if packet.is_err() {
+        let pac = packets::Packet::decode(buf)?;
+        if pac
+            .data
+            .as_ref()
+            .is_some_and(|data| !self.within_limit(addr, data))
+        {
             return Ok(());
         }
-        let pac = packet?;
+        let ctx = &mut self.ctx;
         match pac.data {
             Some(Data::Login(mut login)) => {
-                if self.get_player_by_name(&login.user).is_none() {
-                    if Player::check_account(login.user.clone(), login.password)? {
+                if player::find(&mut self.players, &login.user).is_none() {
+                    if Player::check_account(ctx, login.user.clone(), login.password)? {
                         if login.version == "" {
                             login.version = "0.1.0".to_string();
                         }
                         let version = Version::parse(&login.version)?;
-                        let server_version = Version::parse(env!("CARGO_PKG_VERSION"))?;
+                        let server_version = Version::parse(&ctx.config.client.minimum_version)?;
                         if version < server_version {
-                            let error = packets::Error { reason: format!("A new version is available for download, Your version: {} Latest version: {}, Download the new version from https://arelius.org/coas/coas.zip, If you are running linux, Download from https://arelius.org/coas/coas_linux.zip", version, server_version) };
-                            get_server().send(addr, Data::Error(error))?;
+                            let client = &ctx.config.client;
+                            let error = packets::Error { reason: format!("A new version is available for download, Your version: {} Latest version: {}, Download the new version from {}, If you are running linux, Download from {}", version, server_version, client.download_url, client.linux_download_url) };
+                            ctx.outbox.send(addr, Data::Error(error));
                             return Ok(());
                         }
-                        let banned = self.data.bans.get_user(login.id.clone());
-                        let temporary_banned = self.data.bans.get_temporary(login.id.clone());
-                        if banned || temporary_banned {
-                            let mut error = packets::Error {
-                                reason: String::from("You have been banned..."),
-                            };
-                            if temporary_banned {
-                                error.reason += &format!(
-                                    "Time remaining: {}",
-                                    format_time(
-                                        self.data
-                                            .bans
-                                            .get_temporary_remaining(login.id.clone())
-                                            .try_into()?
-                                    )
-                                )
-                            }
-                            get_server().send(addr, Data::Error(error))?;
+                        let ip = Some(*addr.address().ip());
+                        if let Some(ban) = ctx.data.bans.find(&login.user, &login.id, ip) {
+                            let reason = ban.message(Utc::now());
+                            ctx.outbox
+                                .send(addr, Data::Error(packets::Error { reason }));
                             return Ok(());
                         }
                         let mut players = vec![];
@@ -703,81 +371,91 @@ impl Server {
                         let mut connected = packets::Connected::default();
                         connected.players = players;
                         self.add_player(&login.user, addr, login.id.clone())?;
-                        if let Some(player) = self.get_player(&addr) {
-                            if login.dev.is_some() {
-                                if login.user == "emperor-limitless" {
-                                    player.data.dev = true;
-                                    connected.dev = Some(true);
-                                }
-                            }
-                            if player.admin() {
+                        let ctx = &mut self.ctx;
+                        if let Some(player) = self.players.get(&addr) {
+                            if player.has_permission(&ctx.config, "admin.chat") {
                                 connected.admin = Some(true);
                             }
                         }
-                        self.send(addr, Data::Connected(connected))?;
-                        if let Some(player) = get_server().get_player(&addr) {
+                        ctx.outbox.send(addr, Data::Connected(connected));
+                        if let Some(player) = self.players.get(&addr) {
                             let mut parse = packets::ParseMap::default();
                             parse.data =
-                                std::fs::read_to_string(format!("maps/{}.map", player.data.map))?;
-                            self.send(addr, Data::ParseMap(parse))?;
+                                std::fs::read_to_string(ctx.config.map_path(&player.data.map))?;
+                            ctx.outbox.send(addr, Data::ParseMap(parse));
                             let mut online = packets::Online::default();
                             online.who = login.user;
                             online.x = player.data.x.try_into()?;
                             online.y = player.data.y.try_into()?;
                             online.map = player.data.map.clone();
                             online.direction = player.data.direction.try_into()?;
-                            self.broadcast(Data::Online(online))?;
+                            ctx.outbox.broadcast(Data::Online(online));
                         }
                     } else {
                         let mut error = packets::Error::default();
                         error.reason = "Invalid username or password!".to_string();
-                        self.send(addr, Data::Error(error))?;
+                        ctx.outbox.send(addr, Data::Error(error));
                         return Ok(());
                     }
-                } else if self.get_player_by_name(&login.user).is_some() {
+                } else {
                     let mut error = packets::Error::default();
                     error.reason = "A player with the same name is already logged in!".to_string();
-                    self.send(addr, Data::Error(error))?;
+                    ctx.outbox.send(addr, Data::Error(error));
                     return Ok(());
                 }
             }
             Some(Data::Move(mut mpc)) => {
-                if let Some(player) = self.get_player(&addr) {
-                    if let Some(x) = mpc.x {
-                        player.data.x = x.try_into()?;
-                    }
-                    if let Some(y) = mpc.y {
-                        player.data.y = y.try_into()?;
+                if let Some(player) = self.players.get_mut(&addr) {
+                    let x = mpc.x.map_or(Ok(player.data.x), isize::try_from)?;
+                    let y = mpc.y.map_or(Ok(player.data.y), isize::try_from)?;
+                    if let Some(map) = ctx.maps.get(&player.data.map) {
+                        let from = (player.data.x, player.data.y);
+                        let silent = mpc.silent.is_some();
+                        if let Err(violation) =
+                            player.movement.check(map, ctx.now(), from, (x, y), silent)
+                        {
+                            player.reject_move(ctx, violation)?;
+                            return Ok(());
+                        }
                     }
+                    player.data.x = x;
+                    player.data.y = y;
                     if let Some(direction) = mpc.direction {
                         player.data.direction = direction.try_into()?;
                     }
                     mpc.map = player.data.map.clone();
                     mpc.who = player.name.clone();
-                    get_server().broadcast(Data::Move(mpc.clone()))?;
+                    ctx.outbox
+                        .broadcast_map(Data::Move(mpc.clone()), &player.data.map);
                     if mpc.silent.is_none() {
-                        if let Some(map) = get_server().get_map(&player.data.map) {
+                        if let Some(map) = ctx.maps.get(&player.data.map) {
                             if let Some(tile) = map.get_tile(player.data.x, player.data.y) {
                                 let mut play = packets::Play::default();
                                 play.x = Some(player.data.x.try_into()?);
                                 play.y = Some(player.data.y.try_into()?);
                                 play.who = player.name.clone();
-                                play.sound = format!(
-                                    "steps/{}/step{}.mp3",
-                                    tile,
-                                    get_server().rng.gen_range(1..=5)
-                                );
+                                play.sound =
+                                    format!("steps/{}/step{}.mp3", tile, ctx.rng.gen_range(1..=5));
                                 play.map = player.data.map.clone();
-                                self.broadcast(Data::Play(play))?;
+                                ctx.outbox.broadcast_sound(
+                                    Data::Play(play),
+                                    &player.data.map,
+                                    player.data.x,
+                                    player.data.y,
+                                );
                             }
                         }
                     }
                 }
             }
             Some(Data::Play(mut play)) => {
-                if let Some(player) = self.get_player(&addr) {
+                if let Some(player) = self.players.get(&addr) {
                     play.who = player.name.clone();
-                    self.broadcast(Data::Play(play))?;
+                    play.map = player.data.map.clone();
+                    let x = play.x.map_or(Ok(player.data.x), isize::try_from)?;
+                    let y = play.y.map_or(Ok(player.data.y), isize::try_from)?;
+                    ctx.outbox
+                        .broadcast_sound(Data::Play(play), &player.data.map, x, y);
                 }
             }
             Some(Data::ServerStats(_)) => {
@@ -785,61 +463,57 @@ impl Server {
                 buffer.text = format!(
                     "Server version {}, Up for {}, Peak: {}",
                     env!("CARGO_PKG_VERSION"),
-                    format_time(self.up_timer.elapsed().as_millis()),
-                    self.data.peak
+                    format_time(ctx.up_timer.elapsed().as_millis()),
+                    ctx.data.peak
                 );
-                self.send(addr, Data::Buffer(buffer))?;
+                ctx.outbox.send(addr, Data::Buffer(buffer));
             }
             Some(Data::ServerNote(_)) => {
-                let note = match fs::read_to_string("note.txt") {
+                let note = match fs::read_to_string(ctx.config.note_path()) {
                     Ok(note) => note,
                     Err(_) => "No server note at the moment".to_string(),
                 };
                 let mut buffer = packets::Buffer::default();
                 buffer.text = format!("Server note: {}", note);
-                self.send(addr, Data::Buffer(buffer))?;
+                ctx.outbox.send(addr, Data::Buffer(buffer));
             }
             Some(Data::UseItem(_)) => {
-                if let Some(player) = self.get_player(&addr) {
-                    player.use_item()?;
+                if let Some(player) = self.players.get_mut(&addr) {
+                    player.use_item(ctx)?;
                 }
             }
             Some(Data::Cycle(cycle)) => {
-                if let Some(player) = self.get_player(&addr) {
-                    player.cycle(cycle.direction.try_into()?)?;
+                if let Some(player) = self.players.get_mut(&addr) {
+                    player.cycle(ctx, cycle.direction.try_into()?)?;
                 }
             }
-            Some(Data::Teleport(teleport)) => {
-                if let Some(player) = self.get_player(&addr) {
-                    player.change_map(
-                        teleport.x.try_into()?,
-                        teleport.y.try_into()?,
-                        teleport.map.clone(),
-                    )?;
+            Some(Data::Teleport(_)) => {
+                if let Some(player) = self.players.get_mut(&addr) {
+                    player.use_teleporter(ctx)?;
                 }
             }
             Some(Data::Draw(draw)) => {
-                if let Some(player) = self.get_player(&addr) {
-                    player.draw(draw.weapon.clone())?;
+                if let Some(player) = self.players.get_mut(&addr) {
+                    player.draw(ctx, draw.weapon.clone())?;
                 }
             }
             Some(Data::Fire(_)) => {
-                if let Some(player) = self.get_player(&addr) {
-                    player.fire()?;
+                if let Some(player) = self.players.get_mut(&addr) {
+                    player.fire(ctx)?;
                 }
             }
             Some(Data::Reload(_)) => {
-                if let Some(player) = self.get_player(&addr) {
-                    player.reload()?;
+                if let Some(player) = self.players.get_mut(&addr) {
+                    player.reload(ctx)?;
                 }
             }
             Some(Data::FireStop(_)) => {
-                if let Some(player) = self.get_player(&addr) {
+                if let Some(player) = self.players.get_mut(&addr) {
                     player.stop_fire();
                 }
             }
             Some(Data::Ammo(_)) => {
-                if let Some(player) = self.get_player(&addr) {
+                if let Some(player) = self.players.get(&addr) {
                     let mut say = packets::Say::default();
                     if player.data.weapon.is_empty() {
                         say.text = "You don't have a weapon loaded".to_string();
@@ -850,91 +524,91 @@ impl Server {
                             player.data.get_cartridges()
                         );
                     }
-                    self.send(addr, Data::Say(say))?;
+                    ctx.outbox.send(addr, Data::Say(say));
                 }
             }
             Some(Data::Health(_)) => {
-                if let Some(player) = self.get_player(&addr) {
-                    player.say(format!("{}HP", player.data.health))?;
+                if let Some(player) = self.players.get(&addr) {
+                    player.say(ctx, format!("{}HP", player.data.health))?;
                 }
             }
             Some(Data::Create(create)) => {
-                let banned = self.data.bans.get_user(create.id.clone());
-                let temporary_banned = self.data.bans.get_temporary(create.id.clone());
-                if banned || temporary_banned {
-                    let mut error = packets::Error {
-                        reason: String::from("You have been banned..."),
-                    };
-                    if temporary_banned {
-                        error.reason += &format!(
-                            "Time remaining: {}",
-                            format_time(
-                                self.data
-                                    .bans
-                                    .get_temporary_remaining(create.id.clone())
-                                    .try_into()?
-                            )
-                        )
-                    }
-                    get_server().send(addr, Data::Error(error))?;
+                if let Err(e) = storage::check_name(&create.user) {
+                    let reason = e.to_string();
+                    ctx.outbox
+                        .send(addr, Data::Error(packets::Error { reason }));
+                    return Ok(());
+                }
+                let ip = Some(*addr.address().ip());
+                if let Some(ban) = ctx.data.bans.find(&create.user, &create.id, ip) {
+                    let reason = ban.message(Utc::now());
+                    ctx.outbox
+                        .send(addr, Data::Error(packets::Error { reason }));
                     return Ok(());
                 }
-                if Player::create(addr, create.user.clone(), create.password, create.email)? {
-                    self.send(addr, Data::Created(packets::Created::default()))?;
-                    self.admin_tell(format!("Alert: {} Has been created", create.user))?;
+                if Player::create(
+                    ctx,
+                    addr,
+                    create.user.clone(),
+                    create.password,
+                    create.email,
+                )? {
+                    ctx.outbox
+                        .send(addr, Data::Created(packets::Created::default()));
+                    ctx.outbox
+                        .admin_tell(format!("Alert: {} Has been created", create.user));
                 } else {
                     let mut error = packets::Error::default();
                     error.reason = "A player with that name already exists!".to_string();
-                    self.send(addr, Data::Error(error))?;
+                    ctx.outbox.send(addr, Data::Error(error));
                     return Ok(());
                 }
             }
             Some(Data::Close(_)) => {
                 if let Some(player) = self.players.remove(&addr) {
                     if player.got_hit {
-                        get_server().notify(format!(
+                        ctx.outbox.notify(format!(
                             "{} Have been detected to cheat and is banned for 3 minutes",
                             player.name
-                        ))?;
-                        get_server().data.bans.add_temporary(
-                            player.name.clone(),
-                            player.data.id.clone(),
-                            180000,
-                        );
-                        get_server().save()?;
+                        ));
+                        let ban = Ban::new(&player.name, &player.data.id, "the server", "Cheating");
+                        ctx.data.bans.add(ban.lasting(180000));
+                        ctx.save()?;
                     }
                     let mut offline = packets::Offline::default();
                     offline.who = player.name.clone();
-                    self.broadcast(Data::Offline(offline))?;
-                    player.save()?;
+                    ctx.outbox.broadcast(Data::Offline(offline));
+                    player.save(ctx)?;
                 }
             }
             Some(Data::Chat(chat)) => {
-                if let Some(player) = self.get_player(&addr) {
+                if let Some(player) = self.players.get_mut(&addr) {
                     if chat.message.starts_with("/") {
                         if let Some(msg) = chat.message.strip_prefix("/") {
                             self.process_command(msg, addr)?;
                         }
                     } else {
                         if !player.data.agreed_to_rules {
-                            player.say(format!("You haven't agreed to the game rules yet. Please read the rules by typing /rules, And accept them by typing /agree, Otherwise, Please delete the game"))?;
+                            player.say(ctx, String::from("You haven't agreed to the game rules yet. Please read the rules by typing /rules, And accept them by typing /agree, Otherwise, Please delete the game"))?;
                             return Ok(());
                         }
-                        if player.data.can_chat {
-                            let mut cht = packets::Chat::default();
-                            cht.message = format!("{} says: {}", &player.name, chat.message);
-                            self.broadcast(Data::Chat(cht))?;
-                        } else {
-                            player.say("You are not allowed to chat".to_string())?;
+                        if let Some(mute) = &player.data.mute {
+                            player.say(ctx, mute.message(Utc::now()))?;
+                        } else if let Some(message) = player.filter_chat(ctx, &chat.message)? {
+                            let cht = packets::Chat {
+                                message: format!("{} says: {}", &player.name, message),
+                            };
+                            ctx.outbox.broadcast(Data::Chat(cht));
                         }
                     }
                 }
             }
             Some(Data::Connect(_)) => {
-                self.send(addr, Data::Connect(packets::Connect::default()))?;
+                ctx.outbox
+                    .send(addr, Data::Connect(packets::Connect::default()));
             }
             Some(Data::Ping(_)) => {
-                self.send(addr, Data::Pong(packets::Pong::default()))?;
+                ctx.outbox.send(addr, Data::Pong(packets::Pong::default()));
             }
             Some(Data::Who(_)) => {
                 let mut names = vec![];
@@ -954,98 +628,232 @@ impl Server {
                         names.join(", ")
                     );
                 }
-                self.send(addr, Data::Buffer(buffer))?;
+                ctx.outbox.send(addr, Data::Buffer(buffer));
             }
             _ => (),
         }
         Ok(())
     }
     pub fn add_player(&mut self, user: &str, addr: Peer, id: String) -> anyhow::Result<()> {
-        let mut player = match Player::load(user.to_string(), addr)? {
+        let mut player = match Player::load(&self.ctx, user.to_string(), addr)? {
             Some(player) => player,
             None => return Ok(()),
         };
         player.data.id = id;
+        player.data.ip = Some(*addr.address().ip());
         self.players.insert(addr, player);
-        if self.players.len() > self.data.peak {
-            self.data.peak = self.players.len();
-            self.notify(format!(
+        let ctx = &mut self.ctx;
+        if self.players.len() > ctx.data.peak {
+            ctx.data.peak = self.players.len();
+            ctx.outbox.notify(format!(
                 "We have reached a new peak of  {} players",
-                self.data.peak
-            ))?;
-            self.save()?;
+                ctx.data.peak
+            ));
+            ctx.save()?;
         }
         Ok(())
     }
     pub fn add_connection(&mut self, addr: Peer) {
-        let connection = Connection::new(addr);
+        let connection = Connection::new(addr, self.ctx.now());
         self.connections.insert(addr, connection);
     }
     pub fn players_update(&mut self) -> anyhow::Result<()> {
+        let mut killers = vec![];
         for (_, i) in self.players.iter_mut() {
-            i.update()?;
+            if let Some(killer) = i.update(&mut self.ctx)? {
+                killers.push(killer);
+            }
+        }
+        for killer in killers {
+            if let Some(player) = player::find(&mut self.players, &killer) {
+                player.data.kills += 1;
+            }
         }
         Ok(())
     }
     pub fn connections_update(&mut self) -> anyhow::Result<()> {
         for (_, i) in self.connections.iter_mut() {
-            i.update()?;
+            i.update(&self.ctx.config.limits)?;
         }
         Ok(())
     }
     pub fn weapons_update(&mut self) -> anyhow::Result<()> {
-        for (v, i) in &mut self.weapons.iter_mut().enumerate() {
-            if !i.valid {
-                get_server().weapons.remove(v);
-                return Ok(());
+        // Weapons fired while these move are added to ctx.weapons, so they are
+        // moved out and the new ones appended afterwards.
+        let mut weapons = std::mem::take(&mut self.ctx.weapons);
+        let mut result = Ok(());
+        for i in weapons.iter_mut() {
+            result = i.move_loop(&mut self.players, &mut self.ctx);
+            if result.is_err() {
+                break;
             }
-            i.move_loop()?;
         }
-        Ok(())
+        weapons.retain(|w| w.valid);
+        weapons.append(&mut self.ctx.weapons);
+        self.ctx.weapons = weapons;
+        result
     }
     pub fn maps_update(&mut self) -> anyhow::Result<()> {
-        for (_, i) in &mut self.maps {
-            i.update()?;
+        // The maps are moved out of the context while their spawners get to
+        // the players, nothing a spawner does touches the maps.
+        let mut maps = std::mem::take(&mut self.ctx.maps);
+        let result = maps
+            .values_mut()
+            .try_for_each(|i| i.update(&mut self.players, &mut self.ctx));
+        self.ctx.maps = maps;
+        result
+    }
+    /// Checks a packet against the sender's budget for its kind, returns
+    /// whether it should be handled.
+    fn within_limit(&mut self, peer: Peer, data: &Data) -> bool {
+        let kind = connection::packet_kind(data);
+        let Some(limit) = self.ctx.config.limits.get(kind) else {
+            return true;
+        };
+        let Some(connection) = self.connections.get_mut(&peer) else {
+            return true;
+        };
+        // Admins only hear about the first packet over the limit, a peer being
+        // disconnected has the rest of its burst dropped silently.
+        match connection.spend(kind, limit) {
+            Spent::Allowed => return true,
+            Spent::Over => return false,
+            Spent::FirstOver => {}
         }
-        Ok(())
+        let ip = *peer.address().ip();
+        let who = match self.players.get(&peer) {
+            Some(player) => format!("{} ({})", player.name, ip),
+            None => ip.to_string(),
+        };
+        match limit.action {
+            LimitAction::Drop => {}
+            LimitAction::Warn => {
+                self.ctx.outbox.admin_tell(format!(
+                    "Alert: {} is sending too many {} packets, They are being ignored",
+                    who, kind
+                ));
+            }
+            LimitAction::Disconnect => {
+                println!(
+                    "Disconnecting {} for sending too many {} packets",
+                    who, kind
+                );
+                self.ctx.outbox.admin_tell(format!(
+                    "Alert: {} was disconnected for sending too many {} packets",
+                    who, kind
+                ));
+                self.ctx.outbox.disconnect(peer);
+            }
+        }
+        false
     }
-    pub fn poll(&mut self) -> anyhow::Result<()> {
-        self.data.bans.update()?;
+    /// Logs a packet that couldn't be read or handled and tells its sender,
+    /// peers that keep sending them are disconnected.
+    fn packet_error(&mut self, peer: Peer, error: anyhow::Error) {
+        self.packet_errors += 1;
+        let ip = *peer.address().ip();
+        match self.players.get(&peer) {
+            Some(player) => {
+                println!("Bad packet from {} ({}): {:#}", player.name, ip, error);
+                // An error packet would take a player out of the game.
+                self.ctx.outbox.say(
+                    peer,
+                    String::from("Something went wrong with that, It was ignored"),
+                );
+            }
+            None => {
+                println!("Bad packet from {}: {:#}", ip, error);
+                let error = packets::Error {
+                    reason: String::from("Something went wrong, Please try again"),
+                };
+                self.ctx.outbox.send(peer, Data::Error(error));
+            }
+        }
+        let max = self.ctx.config.network.max_packet_errors;
+        if let Some(connection) = self.connections.get_mut(&peer) {
+            connection.errors += 1;
+            if max > 0 && connection.errors >= max {
+                println!(
+                    "Disconnecting {} after {} bad packets",
+                    ip, connection.errors
+                );
+                self.ctx.outbox.disconnect(peer);
+            }
+        }
+    }
+    /// Handles one network event, waiting up to `timeout` milliseconds for it.
+    /// Returns whether there was one.
+    fn service(&mut self, timeout: u32) -> anyhow::Result<bool> {
+        let Some(recv) = self.host.service(timeout)? else {
+            return Ok(false);
+        };
+        match recv {
+            Event::Receive {
+                ref sender,
+                ref packet,
+                ..
+            } => {
+                if let Err(e) = self.receive(*sender, packet.data()) {
+                    self.packet_error(*sender, e);
+                }
+            }
+            Event::Connect(ref addr) => {
+                println!("New connection: {}", addr.address().ip());
+                self.add_connection(*addr);
+            }
+            Event::Disconnect(ref addr, _) => {
+                println!("New disconnection {}", addr.address().ip());
+                self.connections.remove(addr);
+                if let Some(player) = self.players.remove(addr) {
+                    player.save(&self.ctx)?;
+                    let offline = packets::Offline { who: player.name };
+                    self.ctx.outbox.broadcast(Data::Offline(offline));
+                }
+            }
+        }
+        Ok(true)
+    }
+    /// Advances the game by one tick, handling the network events that
+    /// arrived since the last one first.
+    pub fn tick(&mut self) -> anyhow::Result<()> {
+        while self.service(0)? {}
+        self.ctx.tick += 1;
+        self.ctx.data.bans.update(&mut self.ctx.outbox);
         self.players_update()?;
         self.connections_update()?;
         self.weapons_update()?;
         self.maps_update()?;
-        if let Some(recv) = self.host.service(1)? {
-            match recv {
-                Event::Receive {
-                    ref sender,
-                    ref packet,
-                    ..
-                } => {
-                    if let Ok(data) = &get_server().foo.decrypto(packet.data()) {
-                        self.process_packet(data, *sender)?;
-                    }
+        self.flush()
+    }
+    /// Ticks at `TICK_RATE` until the server is stopped, packets arriving
+    /// between ticks are handled and answered right away.
+    pub fn run(&mut self) -> anyhow::Result<()> {
+        let mut next = Instant::now();
+        while self.running() {
+            let start = Instant::now();
+            self.tick()?;
+            let took = start.elapsed();
+            if took > TICK {
+                println!(
+                    "Tick {} took {}ms, the server is running behind",
+                    self.ctx.tick,
+                    took.as_millis()
+                );
+                // Ticks that were missed are skipped rather than run in a burst.
+                next = Instant::now();
+                continue;
+            }
+            next += TICK;
+            while self.running() {
+                let remaining = next.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
                 }
-                Event::Connect(ref addr) => {
-                    println!("New connection: {}", addr.address().ip());
-                    self.add_connection(*addr);
-                }
-                Event::Disconnect(ref addr, _) => {
-                    println!("New disconnection {}", addr.address().ip());
-                    if self.get_connection(*addr).is_some() {
-                        self.connections.remove(addr);
-                    }
-                    if let Some(player) = self.get_player(addr) {
-                        player.save()?;
-                        let name = player.name.clone();
-                        get_server().players.remove(addr);
-                        let mut offline = packets::Offline::default();
-                        offline.who = name;
-                        get_server().broadcast(Data::Offline(offline))?;
-                    }
+                if self.service(remaining.as_millis().max(1) as u32)? {
+                    self.flush()?;
                 }
             }
         }
-        Ok(())
+        self.shutdown()
     }
 }
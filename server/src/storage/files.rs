@@ -0,0 +1,116 @@
+use super::{check_name, Account, Storage};
+use crate::{dm::Dm, keys::Keys};
+use fernet::Fernet;
+use std::{fs, path::PathBuf};
+
+/// The original layout, `players/<name>/info.player` for the account,
+/// `players/<name>/data.player` for the player and `server.dat`, all
+/// Fernet encrypted JSON.
+pub struct FileStorage {
+    players: PathBuf,
+    server: PathBuf,
+    account_key: String,
+    data_key: String,
+}
+
+impl FileStorage {
+    pub fn new(players: PathBuf, server: PathBuf, keys: &Keys) -> Self {
+        Self {
+            players,
+            server,
+            account_key: keys.accounts.clone(),
+            data_key: keys.data.clone(),
+        }
+    }
+    /// The directory of a player, the name is checked first.
+    fn dir(&self, name: &str) -> anyhow::Result<PathBuf> {
+        check_name(name)?;
+        Ok(self.players.join(name))
+    }
+    fn account_db(&self, name: &str) -> anyhow::Result<Dm> {
+        let path = self.dir(name)?.join("info.player");
+        Ok(Dm::new(
+            path.to_string_lossy().to_string(),
+            self.account_key.clone(),
+        ))
+    }
+    fn read(&self, path: PathBuf) -> anyhow::Result<Option<String>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let f = match Fernet::new(&self.data_key) {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        let text = fs::read_to_string(path)?;
+        Ok(Some(String::from_utf8(f.decrypt(&text)?)?))
+    }
+    fn write(&self, path: PathBuf, data: &str) -> anyhow::Result<()> {
+        let f = match Fernet::new(&self.data_key) {
+            Some(f) => f,
+            None => return Ok(()),
+        };
+        fs::write(path, f.encrypt(data.as_bytes()))?;
+        Ok(())
+    }
+}
+
+impl Storage for FileStorage {
+    fn account_exists(&self, name: &str) -> anyhow::Result<bool> {
+        Ok(self.dir(name)?.exists())
+    }
+    fn load_account(&self, name: &str) -> anyhow::Result<Option<Account>> {
+        if !self.dir(name)?.join("info.player").exists() {
+            return Ok(None);
+        }
+        let mut db = self.account_db(name)?;
+        db.load();
+        Ok(Some(Account {
+            password_hash: db.m.get("password_hash").cloned(),
+            password: db.m.get("password").cloned(),
+            mail: db.get("mail"),
+        }))
+    }
+    fn save_account(&self, name: &str, account: &Account) -> anyhow::Result<()> {
+        fs::create_dir_all(self.dir(name)?)?;
+        let mut db = self.account_db(name)?;
+        if let Some(hash) = &account.password_hash {
+            db.add(String::from("password_hash"), hash.clone());
+        }
+        if let Some(password) = &account.password {
+            db.add(String::from("password"), password.clone());
+        }
+        db.add(String::from("mail"), account.mail.clone());
+        db.save()
+    }
+    fn account_names(&self) -> anyhow::Result<Vec<String>> {
+        let mut names = vec![];
+        if !self.players.exists() {
+            return Ok(names);
+        }
+        for entry in fs::read_dir(&self.players)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+    fn load_player(&self, name: &str) -> anyhow::Result<Option<String>> {
+        self.read(self.dir(name)?.join("data.player"))
+    }
+    fn save_player(&self, name: &str, data: &str) -> anyhow::Result<()> {
+        let dir = self.dir(name)?;
+        if !dir.exists() {
+            anyhow::bail!("Can't save player {} without an account", name);
+        }
+        self.write(dir.join("data.player"), data)
+    }
+    fn load_server(&self) -> anyhow::Result<Option<String>> {
+        self.read(self.server.clone())
+    }
+    fn save_server(&self, data: &str) -> anyhow::Result<()> {
+        self.write(self.server.clone(), data)
+    }
+}
@@ -0,0 +1,77 @@
+//! Where accounts, player data and the server's own data (bans and stats) are kept.
+//!
+//! Player and server data are handed over as the JSON the rest of the server
+//! serializes them to, so backends don't need to know their layout and can
+//! be copied between without loading the game.
+mod files;
+mod sqlite;
+
+use crate::keys::Keys;
+use std::path::Path;
+
+pub use files::FileStorage;
+pub use sqlite::SqliteStorage;
+
+#[derive(Clone, Default)]
+pub struct Account {
+    /// Argon2id hash of the password as a PHC string.
+    pub password_hash: Option<String>,
+    /// Plaintext password of an account that hasn't been upgraded yet.
+    pub password: Option<String>,
+    pub mail: String,
+}
+
+pub trait Storage {
+    fn account_exists(&self, name: &str) -> anyhow::Result<bool>;
+    fn load_account(&self, name: &str) -> anyhow::Result<Option<Account>>;
+    /// Creates the account if it doesn't exist.
+    fn save_account(&self, name: &str, account: &Account) -> anyhow::Result<()>;
+    fn account_names(&self) -> anyhow::Result<Vec<String>>;
+    fn load_player(&self, name: &str) -> anyhow::Result<Option<String>>;
+    fn save_player(&self, name: &str, data: &str) -> anyhow::Result<()>;
+    fn load_server(&self) -> anyhow::Result<Option<String>>;
+    fn save_server(&self, data: &str) -> anyhow::Result<()>;
+}
+
+/// Checks a name before it is used for an account, the files storage makes
+/// paths out of them so they can't leave the players directory.
+pub fn check_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() || name == "." || name.contains("..") || name.contains(['/', '\\', '\0']) {
+        anyhow::bail!("{:?} can't be used as a name", name);
+    }
+    Ok(())
+}
+
+/// Opens a storage backend from its description, `files` for the players
+/// directory and server.dat in the data directory, or `sqlite:<path>` for a database.
+pub fn open(spec: &str, data: &Path, keys: &Keys) -> anyhow::Result<Box<dyn Storage>> {
+    if spec == "files" {
+        return Ok(Box::new(FileStorage::new(
+            data.join("players"),
+            data.join("server.dat"),
+            keys,
+        )));
+    }
+    if let Some(path) = spec.strip_prefix("sqlite:") {
+        return Ok(Box::new(SqliteStorage::open(path)?));
+    }
+    anyhow::bail!("Unknown storage {}, Expected files or sqlite:<path>", spec)
+}
+
+/// Copies every account, player and the server data from one backend to another.
+/// Returns how many accounts were copied.
+pub fn import(from: &dyn Storage, to: &dyn Storage) -> anyhow::Result<usize> {
+    let names = from.account_names()?;
+    for name in &names {
+        if let Some(account) = from.load_account(name)? {
+            to.save_account(name, &account)?;
+        }
+        if let Some(data) = from.load_player(name)? {
+            to.save_player(name, &data)?;
+        }
+    }
+    if let Some(data) = from.load_server()? {
+        to.save_server(&data)?;
+    }
+    Ok(names.len())
+}
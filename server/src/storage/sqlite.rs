@@ -0,0 +1,111 @@
+use super::{check_name, Account, Storage};
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Everything in one SQLite database. Player and server data are stored as
+/// JSON, so they can be queried with SQLite's JSON functions, e.g.
+/// `SELECT name FROM players WHERE json_extract(data, '$.kills') > 100`.
+pub struct SqliteStorage {
+    db: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let db = Connection::open(path)?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS accounts (
+                name TEXT PRIMARY KEY,
+                password_hash TEXT,
+                password TEXT,
+                mail TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS players (
+                name TEXT PRIMARY KEY REFERENCES accounts(name),
+                data TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS server (
+                id INTEGER PRIMARY KEY CHECK (id = 0),
+                data TEXT NOT NULL
+            );",
+        )?;
+        Ok(Self { db })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn account_exists(&self, name: &str) -> anyhow::Result<bool> {
+        let exists = self
+            .db
+            .query_row("SELECT 1 FROM accounts WHERE name = ?1", [name], |_| Ok(()))
+            .optional()?;
+        Ok(exists.is_some())
+    }
+    fn load_account(&self, name: &str) -> anyhow::Result<Option<Account>> {
+        let account = self
+            .db
+            .query_row(
+                "SELECT password_hash, password, mail FROM accounts WHERE name = ?1",
+                [name],
+                |row| {
+                    Ok(Account {
+                        password_hash: row.get(0)?,
+                        password: row.get(1)?,
+                        mail: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(account)
+    }
+    fn save_account(&self, name: &str, account: &Account) -> anyhow::Result<()> {
+        // Checked here too so accounts can always be imported into the files storage.
+        check_name(name)?;
+        self.db.execute(
+            "INSERT INTO accounts (name, password_hash, password, mail) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(name) DO UPDATE SET
+                password_hash = excluded.password_hash,
+                password = excluded.password,
+                mail = excluded.mail",
+            params![name, account.password_hash, account.password, account.mail],
+        )?;
+        Ok(())
+    }
+    fn account_names(&self) -> anyhow::Result<Vec<String>> {
+        let mut statement = self.db.prepare("SELECT name FROM accounts ORDER BY name")?;
+        let names = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(names)
+    }
+    fn load_player(&self, name: &str) -> anyhow::Result<Option<String>> {
+        let data = self
+            .db
+            .query_row("SELECT data FROM players WHERE name = ?1", [name], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(data)
+    }
+    fn save_player(&self, name: &str, data: &str) -> anyhow::Result<()> {
+        self.db.execute(
+            "INSERT INTO players (name, data) VALUES (?1, ?2)
+            ON CONFLICT(name) DO UPDATE SET data = excluded.data",
+            params![name, data],
+        )?;
+        Ok(())
+    }
+    fn load_server(&self) -> anyhow::Result<Option<String>> {
+        let data = self
+            .db
+            .query_row("SELECT data FROM server WHERE id = 0", [], |row| row.get(0))
+            .optional()?;
+        Ok(data)
+    }
+    fn save_server(&self, data: &str) -> anyhow::Result<()> {
+        self.db.execute(
+            "INSERT INTO server (id, data) VALUES (0, ?1)
+            ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            [data],
+        )?;
+        Ok(())
+    }
+}
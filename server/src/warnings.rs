@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// A warning given to a player, they add up towards the penalties in server.toml.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Warning {
+    pub issuer: String,
+    pub reason: String,
+    pub created: DateTime<Utc>,
+}
+
+impl Warning {
+    pub fn new(issuer: &str, reason: &str) -> Self {
+        Self {
+            issuer: issuer.to_string(),
+            reason: reason.to_string(),
+            created: Utc::now(),
+        }
+    }
+    /// A line of a warning list.
+    pub fn describe(&self) -> String {
+        format!(
+            "By {} on {}, Reason: {}",
+            self.issuer,
+            self.created.format("%Y-%m-%d %H:%M UTC"),
+            self.reason
+        )
+    }
+}
+
+/// The numbered lines of a warning list.
+pub fn list(warnings: &[Warning]) -> String {
+    warnings
+        .iter()
+        .enumerate()
+        .map(|(i, w)| format!("{}. {}", i + 1, w.describe()))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
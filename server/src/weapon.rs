@@ -1,15 +1,18 @@
-use crate::server::get_server;
+use crate::{
+    player::{self, Players},
+    server::Context,
+};
 use rand::Rng;
-use std::time::Instant;
 
 pub struct Weapon {
     damage: isize,
     speed: u128,
     range: usize,
     pos: usize,
-    move_timer: Instant,
+    /// The game time of the last step.
+    move_timer: u128,
     owner: String,
-    name: String,
+    sounds: String,
     map: String,
     x: isize,
     y: isize,
@@ -19,6 +22,7 @@ pub struct Weapon {
 
 impl Weapon {
     pub fn new(
+        ctx: &Context,
         x: isize,
         y: isize,
         facing: usize,
@@ -29,101 +33,108 @@ impl Weapon {
         let mut damage = 0;
         let mut speed = 20;
         let mut range = 0;
-        if name == "pistol" {
-            damage = 210;
-            range = 20;
-            speed = 25;
-        } else if name == "machinegun" {
-            range = 30;
-            speed = 10;
-            damage = 30;
-        } else if name == "grenade_launcher" {
-            range = 60;
-            speed = 5;
-            damage = 400;
+        let mut sounds = name.clone();
+        if let Some(weapon) = ctx.weapon_registry.get(&name) {
+            damage = weapon.damage;
+            speed = weapon.speed.into();
+            range = weapon.range;
+            sounds = weapon.sounds.clone();
         }
         Self {
             damage,
             speed,
             range,
+            sounds,
             pos: 0,
-            move_timer: Instant::now(),
+            move_timer: ctx.now(),
             owner,
             map,
-            name,
             x,
             y,
             facing,
             valid: true,
         }
     }
-    pub fn move_loop(&mut self) -> anyhow::Result<()> {
-        if !self.valid {
-            return Ok(());
+    pub fn boost(&mut self, percent: isize) {
+        self.damage += self.damage * percent / 100;
+    }
+    /// Takes every step the projectile's speed allows since the last tick,
+    /// stopping at the first wall or player it hits.
+    pub fn move_loop(&mut self, players: &mut Players, ctx: &mut Context) -> anyhow::Result<()> {
+        // Players can walk into a projectile between its steps.
+        self.hit(players, ctx)?;
+        while self.valid && ctx.now() - self.move_timer >= self.speed {
+            self.move_timer += self.speed;
+            self.step(ctx)?;
+            self.hit(players, ctx)?;
         }
-        if self.move_timer.elapsed().as_millis() >= self.speed {
-            self.move_timer = Instant::now();
-            match self.facing {
-                0 => {
-                    self.x += 1;
-                }
-                1 => {
-                    self.x -= 1;
-                }
-                2 => {
-                    self.y += 1;
-                }
-                3 => {
-                    self.y -= 1;
-                }
-                _ => (),
+        Ok(())
+    }
+    fn step(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+        match self.facing {
+            0 => {
+                self.x += 1;
             }
-            if let Some(map) = get_server().get_map(&self.map) {
-                if let Some(tile) = map.get_tile(self.x, self.y) {
-                    if tile.contains("wall") {
-                        self.valid = false;
-                        get_server().play(
-                            &format!("walls/{}.mp3", tile),
-                            self.x,
-                            self.y,
-                            &self.map,
-                        )?;
-                    }
-                }
+            1 => {
+                self.x -= 1;
+            }
+            2 => {
+                self.y += 1;
             }
-            self.pos += 1;
-            if self.pos > self.range {
-                self.valid = false;
-                return Ok(());
+            3 => {
+                self.y -= 1;
             }
+            _ => (),
         }
-        for (_, i) in &mut get_server().players {
+        if let Some(map) = ctx.maps.get(&self.map) {
+            if let Some(tile) = map.get_tile(self.x, self.y) {
+                if tile.contains("wall") {
+                    self.valid = false;
+                    ctx.outbox
+                        .play(&format!("walls/{}.mp3", tile), self.x, self.y, &self.map)?;
+                }
+            }
+        }
+        self.pos += 1;
+        if self.pos > self.range {
+            self.valid = false;
+        }
+        Ok(())
+    }
+    fn hit(&mut self, players: &mut Players, ctx: &mut Context) -> anyhow::Result<()> {
+        if !self.valid {
+            return Ok(());
+        }
+        let mut hit = false;
+        for (_, i) in players.iter_mut() {
             if !i.data.safe
                 && i.name != self.owner
                 && i.data.map == self.map
                 && i.data.x == self.x
                 && i.data.y == self.y
             {
-                i.data.health -= self.damage;
+                i.take_damage(self.damage);
                 i.data.last_hit = self.owner.clone();
                 i.got_hit = true;
-                i.cheat_timer = Instant::now();
-                i.play(format!(
-                    "player/pain{}.mp3",
-                    get_server().rng.gen_range(1..=3)
-                ))?;
-                i.play(format!(
+                i.cheat_timer = ctx.now();
+                let pain = format!("player/pain{}.mp3", ctx.rng.gen_range(1..=3));
+                i.play(ctx, pain)?;
+                let sound = format!(
                     "weapons/{}/hit{}.mp3",
-                    self.name,
-                    get_server().rng.gen_range(1..=3)
-                ))?;
-                if let Some(player) = get_server().get_player_by_name(&self.owner) {
-                    if player.data.hit_ping {
-                        player.self_play("notifications/dialog.mp3".to_string())?;
-                    }
+                    self.sounds,
+                    ctx.rng.gen_range(1..=3)
+                );
+                i.play(ctx, sound)?;
+                hit = true;
+                break;
+            }
+        }
+        if hit {
+            self.valid = false;
+            if let Some(player) = player::find(players, &self.owner) {
+                if player.data.hit_ping {
+                    player.self_play(ctx, "notifications/dialog.mp3".to_string())?;
                 }
-                self.valid = false;
-                return Ok(());
             }
         }
         Ok(())
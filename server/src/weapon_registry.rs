@@ -0,0 +1,127 @@
+use indexmap::IndexMap;
+use serde_derive::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+const DEFAULT_WEAPONS: &str = include_str!("weapons.toml");
+
+#[derive(Deserialize, Default, Clone)]
+pub struct Loadout {
+    #[serde(default)]
+    pub weapons: isize,
+    #[serde(default)]
+    pub ammo: isize,
+    #[serde(default)]
+    pub cartridges: isize,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct WeaponDefinition {
+    pub damage: isize,
+    pub speed: u64,
+    pub range: usize,
+    pub fire_time: u64,
+    pub reload_time: u64,
+    pub magazine: isize,
+    #[serde(default)]
+    pub automatic: bool,
+    pub sounds: String,
+    #[serde(default)]
+    pub starting: Loadout,
+}
+
+impl WeaponDefinition {
+    pub fn sound(&self, name: &str) -> String {
+        format!("weapons/{}/{}.mp3", self.sounds, name)
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct WeaponRegistry {
+    weapons: IndexMap<String, WeaponDefinition>,
+}
+
+impl WeaponRegistry {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let registry: WeaponRegistry = toml::from_str(text)?;
+        registry.validate()?;
+        Ok(registry)
+    }
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            println!(
+                "{} not found, Creating it with the default weapons",
+                path.display()
+            );
+            fs::write(path, DEFAULT_WEAPONS)?;
+        }
+        let text = fs::read_to_string(path)?;
+        match Self::parse(&text) {
+            Ok(registry) => Ok(registry),
+            Err(e) => anyhow::bail!("Invalid weapons file {}: {}", path.display(), e),
+        }
+    }
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.weapons.is_empty() {
+            anyhow::bail!("No weapons are defined");
+        }
+        for (name, weapon) in &self.weapons {
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                anyhow::bail!("Weapon name '{}' must be a single word", name);
+            }
+            if weapon.damage < 0 {
+                anyhow::bail!("{}: damage can't be negative", name);
+            }
+            if weapon.speed == 0 {
+                anyhow::bail!("{}: speed must be at least 1 millisecond", name);
+            }
+            if weapon.range == 0 {
+                anyhow::bail!("{}: range must be at least 1 tile", name);
+            }
+            if weapon.fire_time == 0 {
+                anyhow::bail!("{}: fire_time must be at least 1 millisecond", name);
+            }
+            if weapon.magazine <= 0 {
+                anyhow::bail!("{}: magazine must hold at least 1 round", name);
+            }
+            if weapon.sounds.is_empty() || weapon.sounds.contains("..") {
+                anyhow::bail!("{}: sounds must be a folder name under weapons/", name);
+            }
+            let starting = &weapon.starting;
+            if starting.weapons < 0 || starting.ammo < 0 || starting.cartridges < 0 {
+                anyhow::bail!("{}: the starting loadout can't be negative", name);
+            }
+            if starting.ammo > weapon.magazine {
+                anyhow::bail!("{}: starting ammo doesn't fit in the magazine", name);
+            }
+        }
+        Ok(())
+    }
+    pub fn get(&self, name: &str) -> Option<&WeaponDefinition> {
+        self.weapons.get(name)
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.weapons.contains_key(name)
+    }
+    fn starting<F>(&self, field: F) -> HashMap<String, isize>
+    where
+        F: Fn(&Loadout) -> isize,
+    {
+        let mut loadout = HashMap::new();
+        for (name, weapon) in &self.weapons {
+            let amount = field(&weapon.starting);
+            if amount > 0 {
+                loadout.insert(name.clone(), amount);
+            }
+        }
+        loadout
+    }
+    pub fn starting_weapons(&self) -> HashMap<String, isize> {
+        self.starting(|l| l.weapons)
+    }
+    pub fn starting_ammo(&self) -> HashMap<String, isize> {
+        self.starting(|l| l.ammo)
+    }
+    pub fn starting_cartridges(&self) -> HashMap<String, isize> {
+        self.starting(|l| l.cartridges)
+    }
+}
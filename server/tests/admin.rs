@@ -0,0 +1,275 @@
+mod common;
+use coas_server::packets::{self, packet::Data};
+use common::TestServer;
+use std::time::Duration;
+
+/// A server where boss owns everything.
+fn start() -> TestServer {
+    TestServer::start_with_owner("boss", |_| {})
+}
+
+#[test]
+fn commands_need_permission() {
+    let mut server = start();
+    let _boss = server.join("boss");
+    let mut alice = server.join("alice");
+    alice.chat("/ban boss");
+    assert_eq!(
+        alice.expect_say(&mut server),
+        "Unknown command /ban, Type /help for a list of commands"
+    );
+}
+
+#[test]
+fn banned_players_cant_log_in() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    let mut victim = server.join("victim");
+    boss.chat("/ban victim spamming");
+    victim.expect(&mut server, |data| match data {
+        Data::Close(_) => Some(()),
+        _ => None,
+    });
+    let mut again = server.connect();
+    again.send_login("victim", "password");
+    assert_eq!(
+        again.expect_error(&mut server),
+        "You have been banned by boss, Reason: spamming"
+    );
+    boss.chat("/ban victim");
+    assert_eq!(
+        boss.expect_say(&mut server),
+        "victim is already banned, Use /unban first"
+    );
+    boss.chat("/unban victim");
+    again.send_login("victim", "password");
+    again.expect(&mut server, |data| match data {
+        Data::Connected(_) => Some(()),
+        Data::Error(error) => panic!("Still banned: {}", error.reason),
+        _ => None,
+    });
+}
+
+#[test]
+fn temporary_bans_tell_the_time_left() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    let victim = server.join("victim");
+    drop(victim);
+    boss.chat("/tempban victim 2h");
+    let mut again = server.connect();
+    again.send_login("victim", "password");
+    let error = again.expect_error(&mut server);
+    assert!(
+        error.starts_with("You have been banned by boss, Time remaining: 1 hour, 59 minutes"),
+        "{}",
+        error
+    );
+    boss.chat("/banlist");
+    let list = boss.expect(&mut server, |data| match data {
+        Data::Buffer(buffer) if buffer.name.is_empty() => Some(buffer.text.clone()),
+        _ => None,
+    });
+    assert!(list.contains("by boss"), "{}", list);
+    assert!(list.contains("remaining"), "{}", list);
+}
+
+#[test]
+fn ip_bans_keep_other_accounts_out() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    let _victim = server.join("victim");
+    boss.chat("/ipban victim");
+    let mut other = server.connect();
+    other.send(Data::Create(packets::Create {
+        user: String::from("sneaky"),
+        password: String::from("password"),
+        email: String::new(),
+        id: String::from("new-device"),
+    }));
+    assert_eq!(
+        other.expect_error(&mut server),
+        "You have been banned by boss"
+    );
+}
+
+#[test]
+fn warnings_add_up_to_mutes_and_bans() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    let mut alice = server.join("alice");
+    alice.chat("/agree");
+    alice.expect_say(&mut server);
+    for i in 1..=3 {
+        boss.chat(&format!("/warn alice spam {}", i));
+        assert_eq!(
+            alice.expect_say(&mut server),
+            format!(
+                "You have been warned by boss, Reason: spam {}, You have {} warnings",
+                i, i
+            )
+        );
+    }
+    assert_eq!(
+        alice.expect_buffer(&mut server, "notifications"),
+        "alice Have been muted by boss For 1 hour, Reason: Reached 3 warnings"
+    );
+    alice.chat("hello");
+    let muted = alice.expect_say(&mut server);
+    assert!(
+        muted.starts_with(
+            "You have been muted by boss, Reason: Reached 3 warnings, Time remaining: 59 minutes"
+        ),
+        "{}",
+        muted
+    );
+    alice.chat("/mywarnings");
+    let list = alice.expect(&mut server, |data| match data {
+        Data::Buffer(buffer) if buffer.name.is_empty() => Some(buffer.text.clone()),
+        _ => None,
+    });
+    assert_eq!(list.lines().count(), 3, "{}", list);
+    assert!(list.starts_with("1. By boss on "), "{}", list);
+    assert!(list.ends_with("Reason: spam 3"), "{}", list);
+    boss.chat("/warn alice spam 4");
+    alice.expect(&mut server, |data| match data {
+        Data::Close(_) => Some(()),
+        _ => None,
+    });
+    let mut again = server.connect();
+    again.send_login("alice", "password");
+    let error = again.expect_error(&mut server);
+    assert!(
+        error.starts_with("You have been banned by boss, Reason: Reached 4 warnings"),
+        "{}",
+        error
+    );
+    boss.chat("/warnings alice");
+    let list = boss.expect(&mut server, |data| match data {
+        Data::Buffer(buffer) if buffer.name.is_empty() => Some(buffer.text.clone()),
+        _ => None,
+    });
+    assert_eq!(list.lines().count(), 4, "{}", list);
+}
+
+#[test]
+fn mutes_end_on_their_own() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    let mut alice = server.join("alice");
+    alice.chat("/agree");
+    alice.expect_say(&mut server);
+    boss.chat("/mute alice 1s rude");
+    assert_eq!(
+        alice.expect_buffer(&mut server, "notifications"),
+        "alice Have been muted by boss For 1 second, Reason: rude"
+    );
+    alice.chat("hello");
+    let muted = alice.expect_say(&mut server);
+    assert!(
+        muted.starts_with("You have been muted by boss, Reason: rude, Time remaining: "),
+        "{}",
+        muted
+    );
+    server.wait(Duration::from_millis(1100));
+    assert_eq!(
+        alice.expect_say(&mut server),
+        "Your mute has ended, You can chat again"
+    );
+    alice.chat("hello");
+    assert_eq!(
+        boss.expect(&mut server, |data| match data {
+            Data::Chat(chat) => Some(chat.message.clone()),
+            _ => None,
+        }),
+        "alice says: hello"
+    );
+}
+
+#[test]
+fn silent_mutes_are_only_told_to_admins() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    let mut alice = server.join("alice");
+    assert_eq!(
+        boss.expect_buffer(&mut server, "admin alerts"),
+        "Alert: alice Has been created"
+    );
+    boss.chat("/silentmute alice 10m");
+    assert_eq!(
+        boss.expect_buffer(&mut server, "admin alerts"),
+        "alice Have been muted by boss For 10 minutes, Silently"
+    );
+    alice.refute(
+        &mut server,
+        Duration::from_millis(200),
+        |data| matches!(data, Data::Buffer(buffer) if buffer.name == "notifications"),
+    );
+    boss.chat("/unmute alice");
+    assert_eq!(
+        alice.expect_say(&mut server),
+        "You have been unmuted, You can chat again"
+    );
+    assert_eq!(
+        boss.expect_buffer(&mut server, "admin alerts"),
+        "alice Have been unmuted by boss"
+    );
+    boss.chat("/unmute alice");
+    assert_eq!(boss.expect_say(&mut server), "alice is not muted");
+}
+
+#[test]
+fn edited_maps_are_sent_to_players_on_them() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    let mut alice = server.join("alice");
+    let map = "map main\nmaxx 20\ntile 0 20 0 0 grass\ntile 1 1 0 0 wall";
+    boss.chat(&format!("/rawdata {}", map));
+    assert_eq!(alice.expect_map(&mut server), map);
+    alice.move_to(1, 0, 0);
+    let corrected = alice.expect(&mut server, |data| match data {
+        Data::MoveClient(m) => Some(m.x),
+        _ => None,
+    });
+    assert_eq!(corrected, Some(0));
+}
+
+#[test]
+fn missing_ambience_files_are_warned_about() {
+    let mut server = TestServer::start_with_owner("boss", |config| {
+        config.paths.client = Some(config.paths.data.clone());
+    });
+    let mut boss = server.join("boss");
+    boss.chat("/rawdata map main\nmaxx 20\ntile 0 20 0 0 grass\nambience 0 5 0 0 sounds/wind.mp3");
+    assert_eq!(
+        boss.expect_say(&mut server),
+        "warning: line 4: ambience file sounds/wind.mp3 doesn't exist"
+    );
+}
+
+#[test]
+fn new_maps_need_a_plain_name() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    boss.chat("/newmap ../../x 10 10 grass");
+    assert_eq!(
+        boss.expect_say(&mut server),
+        "Error: name: ../../x can't be used as a map name, Use letters, numbers, _ and -, Usage: /newmap <name> <maxx> <maxy> <tile>"
+    );
+    assert!(!server.server.ctx.config.paths.data.join("x.map").exists());
+    boss.chat("/newmap new_map-2 10 10 grass");
+    let map = boss.expect_map(&mut server);
+    assert!(map.starts_with("map new_map-2\n"), "{}", map);
+}
+
+#[test]
+fn broken_maps_are_not_saved() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    boss.chat("/rawdata map main\nmaxx 20\ntile 0 20 0 grass");
+    boss.expect(&mut server, |data| match data {
+        Data::Say(say) if say.text == "The map was not saved" => Some(()),
+        Data::ParseMap(_) => panic!("The broken map was sent"),
+        _ => None,
+    });
+}
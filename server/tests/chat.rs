@@ -0,0 +1,113 @@
+mod common;
+use coas_server::packets::packet::Data;
+use common::{TestClient, TestServer};
+use std::time::Duration;
+
+fn expect_chat(client: &mut TestClient, server: &mut TestServer) -> String {
+    client.expect(server, |data| match data {
+        Data::Chat(chat) => Some(chat.message.clone()),
+        _ => None,
+    })
+}
+
+fn agreed(server: &mut TestServer, user: &str) -> TestClient {
+    let mut client = server.join(user);
+    client.chat("/agree");
+    client.expect_say(server);
+    client
+}
+
+#[test]
+fn blocked_messages_warn_and_then_mute() {
+    let mut server = TestServer::start_with_owner("boss", |_| {});
+    let mut boss = server.join("boss");
+    let mut alice = agreed(&mut server, "alice");
+    alice.chat("hi");
+    assert_eq!(expect_chat(&mut boss, &mut server), "alice says: hi");
+    alice.chat("HI ");
+    assert_eq!(
+        alice.expect_say(&mut server),
+        "You just sent that, Your message was not sent, Keep this up and you will be muted"
+    );
+    let alert = boss.expect(&mut server, |data| match data {
+        Data::Buffer(buffer) if buffer.text.starts_with("Alert: A message") => {
+            Some(buffer.text.clone())
+        }
+        _ => None,
+    });
+    assert_eq!(
+        alert,
+        "Alert: A message from alice was blocked, You just sent that"
+    );
+    alice.chat("sooooooooooo long");
+    assert_eq!(
+        expect_chat(&mut boss, &mut server),
+        "alice says: soooo long"
+    );
+    alice.chat("<silence msec=\"100000\"/> hello");
+    assert_eq!(
+        alice.expect_say(&mut server),
+        "Your message contains something that isn't allowed, Your message was not sent, Keep this up and you will be muted"
+    );
+    alice.chat(&"word ".repeat(100));
+    assert_eq!(
+        alice.expect_buffer(&mut server, "notifications"),
+        "alice Have been muted by the server For 10 minutes, Reason: Spamming"
+    );
+    alice.chat("sorry");
+    let muted = alice.expect_say(&mut server);
+    assert!(
+        muted.starts_with("You have been muted by the server, Reason: Spamming"),
+        "{}",
+        muted
+    );
+}
+
+#[test]
+fn admin_tells_are_filtered_and_muted_too() {
+    let mut server = TestServer::start_with_owner("boss", |_| {});
+    let mut boss = server.join("boss");
+    let mut alice = server.join("alice");
+    alice.chat("/admintell <silence msec=\"100000\"/>");
+    assert_eq!(
+        alice.expect_say(&mut server),
+        "Your message contains something that isn't allowed, Your message was not sent, Keep this up and you will be muted"
+    );
+    boss.refute(
+        &mut server,
+        Duration::from_millis(200),
+        |data| matches!(data, Data::Buffer(buffer) if buffer.text.contains("silence")),
+    );
+    alice.chat("/admintell help please");
+    assert_eq!(
+        boss.expect(&mut server, |data| match data {
+            Data::Buffer(buffer) if buffer.text.starts_with("Admin tell") => {
+                Some(buffer.text.clone())
+            }
+            _ => None,
+        }),
+        "Admin tell from alice: help please"
+    );
+    boss.chat("/mute alice 10m");
+    alice.expect_buffer(&mut server, "notifications");
+    alice.chat("/admintell let me talk");
+    let muted = alice.expect_say(&mut server);
+    assert!(
+        muted.starts_with("You have been muted by boss, Time remaining: "),
+        "{}",
+        muted
+    );
+}
+
+#[test]
+fn chat_floods_are_rate_limited() {
+    let mut server = TestServer::start_with(|config| config.chat.violations = 0);
+    let mut alice = agreed(&mut server, "alice");
+    for i in 0..6 {
+        alice.chat(&format!("message {}", i));
+    }
+    assert_eq!(
+        alice.expect_say(&mut server),
+        "You are sending messages too quickly, Your message was not sent, Keep this up and you will be muted"
+    );
+}
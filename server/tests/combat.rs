@@ -0,0 +1,29 @@
+mod common;
+use coas_server::packets::{self, packet::Data};
+use common::TestServer;
+use std::time::Duration;
+
+#[test]
+fn shots_hurt_the_player_in_line() {
+    let mut server = TestServer::start_with_owner("shooter", |_| {});
+    let mut shooter = server.join("shooter");
+    let mut target = server.join("target");
+    shooter.chat("/move target 1 0");
+    target.expect(&mut server, |data| match data {
+        Data::MoveClient(_) => Some(()),
+        _ => None,
+    });
+    shooter.send(Data::Draw(packets::Draw {
+        weapon: String::from("pistol"),
+    }));
+    assert_eq!(shooter.expect_say(&mut server), "pistol");
+    // A player can't fire before the weapon's fire time passed since they joined.
+    server.wait(Duration::from_millis(400));
+    shooter.send(Data::Fire(packets::Fire {}));
+    target.expect(&mut server, |data| match data {
+        Data::Play(play) if play.sound.starts_with("player/pain") => Some(()),
+        _ => None,
+    });
+    target.send(Data::Health(packets::Health {}));
+    assert_eq!(target.expect_say(&mut server), "2790HP");
+}
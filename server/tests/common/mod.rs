@@ -0,0 +1,341 @@
+//! Runs a server on a free localhost port with scripted clients that speak the
+//! real protobuf and transport framing, everything is ticked from the test's thread.
+#![allow(dead_code)]
+use coas_server::{
+    keys::Keys,
+    packets::{self, packet::Data},
+    server, Server, ServerConfig,
+};
+use coas_transport::{Handshake, Role, Session};
+use enet::{Address, BandwidthLimit, ChannelLimit, Event, Host, Packet, PacketMode, Peer};
+use fernet::Fernet;
+use prost::Message;
+use std::{
+    collections::VecDeque,
+    fs,
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
+use tempfile::TempDir;
+
+/// How long a client waits for a packet before the test fails.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A floor with a wall at x 2.
+pub const MAIN_MAP: &str = "map main
+maxx 20
+tile 0 20 0 0 grass
+tile 2 2 0 0 wall";
+
+const SAFE_ZONE_MAP: &str = "map safe_zone
+maxx 50
+tile 0 50 0 0 grass
+safe_zone 0 50 0 0";
+
+pub struct TestServer {
+    pub server: Server,
+    transport: String,
+    dir: TempDir,
+}
+
+impl TestServer {
+    pub fn start() -> Self {
+        Self::start_with(|_| {})
+    }
+    /// A server where `owner` holds the owner role, `setup` changes the rest
+    /// of the config.
+    pub fn start_with_owner<F>(owner: &str, setup: F) -> Self
+    where
+        F: FnOnce(&mut ServerConfig),
+    {
+        Self::start_with(|config| {
+            config
+                .members
+                .insert(owner.to_string(), vec![String::from("owner")]);
+            setup(config);
+        })
+    }
+    /// Starts a server after `setup` had a chance to change its config, with its
+    /// data, maps and registries in a temporary directory.
+    pub fn start_with<F>(setup: F) -> Self
+    where
+        F: FnOnce(&mut ServerConfig),
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let maps = dir.path().join("maps");
+        fs::create_dir(&maps).unwrap();
+        fs::write(maps.join("main.map"), MAIN_MAP).unwrap();
+        fs::write(maps.join("safe_zone.map"), SAFE_ZONE_MAP).unwrap();
+        fs::create_dir(dir.path().join("players")).unwrap();
+        let mut config = ServerConfig::parse(include_str!("../../src/server.toml")).unwrap();
+        config.network.bind = Ipv4Addr::LOCALHOST;
+        config.network.port = 0;
+        config.paths.data = dir.path().to_path_buf();
+        config.paths.maps = maps;
+        config.paths.keys = dir.path().join("keys.toml");
+        config.paths.weapons = dir.path().join("weapons.toml");
+        config.paths.items = dir.path().join("items.toml");
+        setup(&mut config);
+        let keys = Keys::generate();
+        let transport = keys.transport.clone();
+        let mut server = Server::with_keys(config, keys).unwrap();
+        server.load().unwrap();
+        Self {
+            server,
+            transport,
+            dir,
+        }
+    }
+    pub fn tick(&mut self) {
+        self.server.tick().unwrap();
+    }
+    /// Ticks the server for `time` of game time, as fast as it can.
+    pub fn wait(&mut self, time: Duration) {
+        for _ in 0..time.as_millis() / server::TICK.as_millis() {
+            self.tick();
+        }
+    }
+    /// Connects a new client and does the session handshake.
+    pub fn connect(&mut self) -> TestClient {
+        let mut client = self.connect_fernet();
+        let handshake = Handshake::new();
+        client.send_raw(&handshake.hello());
+        let start = Instant::now();
+        loop {
+            self.tick();
+            if let Some(Event::Receive { ref packet, .. }) = client.host.service(0).unwrap() {
+                client.session = Some(handshake.finish(packet.data(), Role::Client).unwrap());
+                return client;
+            }
+            assert!(
+                start.elapsed() < TIMEOUT,
+                "The server never answered the hello"
+            );
+        }
+    }
+    /// Connects a client that encrypts with Fernet like older clients do.
+    pub fn connect_fernet(&mut self) -> TestClient {
+        let enet = server::enet().unwrap();
+        let mut host = enet
+            .create_host::<()>(
+                None,
+                1,
+                ChannelLimit::Maximum,
+                BandwidthLimit::Unlimited,
+                BandwidthLimit::Unlimited,
+            )
+            .unwrap();
+        let port = self.server.address().port();
+        host.connect(&Address::new(Ipv4Addr::LOCALHOST, port), 1, 0)
+            .unwrap();
+        let start = Instant::now();
+        loop {
+            self.tick();
+            if let Some(Event::Connect(peer)) = host.service(1).unwrap() {
+                return TestClient {
+                    host,
+                    peer,
+                    fernet: Fernet::new(&self.transport).unwrap(),
+                    session: None,
+                    last_sent: vec![],
+                    received: VecDeque::new(),
+                    disconnected: false,
+                };
+            }
+            assert!(start.elapsed() < TIMEOUT, "Couldn't connect to the server");
+        }
+    }
+    /// Creates an account and logs it in on a new client.
+    pub fn join(&mut self, user: &str) -> TestClient {
+        let mut client = self.connect();
+        client.create(self, user);
+        client.login(self, user);
+        client
+    }
+}
+
+pub struct TestClient {
+    host: Host<()>,
+    peer: Peer,
+    fernet: Fernet,
+    session: Option<Session>,
+    /// The bytes last sent, to replay them.
+    last_sent: Vec<u8>,
+    received: VecDeque<Data>,
+    disconnected: bool,
+}
+
+impl TestClient {
+    pub fn send(&mut self, data: Data) {
+        let packet = packets::Packet { data: Some(data) };
+        self.send_encrypted(&packet.encode_to_vec());
+    }
+    /// Encrypts bytes the way packets are, whether or not they are a packet.
+    pub fn send_encrypted(&mut self, bytes: &[u8]) {
+        match self.session.as_mut() {
+            Some(session) => {
+                let packet = session.seal(bytes).unwrap();
+                self.send_raw(&packet);
+            }
+            None => {
+                let text = self.fernet.encrypt(bytes);
+                self.send_raw(text.as_bytes());
+            }
+        }
+    }
+    /// Sends bytes as they are, without the protobuf and Fernet framing.
+    pub fn send_raw(&mut self, bytes: &[u8]) {
+        self.last_sent = bytes.to_vec();
+        self.peer
+            .send_packet(
+                Packet::new(bytes, PacketMode::ReliableSequenced).unwrap(),
+                0,
+            )
+            .unwrap();
+        self.host.flush();
+    }
+    /// Sends the last packet again exactly as it was.
+    pub fn resend_last(&mut self) {
+        let bytes = self.last_sent.clone();
+        self.send_raw(&bytes);
+    }
+    /// Queues every packet the server sent so far.
+    fn receive(&mut self) {
+        while let Some(event) = self.host.service(0).unwrap() {
+            match event {
+                Event::Receive { ref packet, .. } => {
+                    let bytes = match self.session.as_mut() {
+                        Some(session) => session.open(packet.data()).unwrap(),
+                        None => {
+                            let text = String::from_utf8(packet.data().to_vec()).unwrap();
+                            self.fernet.decrypt(&text).unwrap()
+                        }
+                    };
+                    if let Some(data) = packets::Packet::decode(bytes.as_slice()).unwrap().data {
+                        self.received.push_back(data);
+                    }
+                }
+                Event::Disconnect(..) => self.disconnected = true,
+                Event::Connect(_) => (),
+            }
+        }
+    }
+    /// Ticks the server until it disconnects this client.
+    pub fn expect_disconnect(&mut self, server: &mut TestServer) {
+        let start = Instant::now();
+        while !self.disconnected {
+            assert!(start.elapsed() < TIMEOUT, "The server never disconnected");
+            server.tick();
+            self.receive();
+        }
+    }
+    /// Polls the server until a packet `find` accepts arrives, the packets
+    /// before it are dropped.
+    pub fn expect<T, F>(&mut self, server: &mut TestServer, mut find: F) -> T
+    where
+        F: FnMut(&Data) -> Option<T>,
+    {
+        let start = Instant::now();
+        let mut skipped = vec![];
+        loop {
+            while let Some(data) = self.received.pop_front() {
+                match find(&data) {
+                    Some(found) => return found,
+                    None => skipped.push(data),
+                }
+            }
+            assert!(
+                start.elapsed() < TIMEOUT,
+                "The packet never arrived, Got: {:?}",
+                skipped
+            );
+            server.tick();
+            self.receive();
+        }
+    }
+    /// Fails if a packet `find` accepts arrives within `time`.
+    pub fn refute<F>(&mut self, server: &mut TestServer, time: Duration, mut find: F)
+    where
+        F: FnMut(&Data) -> bool,
+    {
+        let start = Instant::now();
+        while start.elapsed() < time {
+            server.tick();
+            self.receive();
+            while let Some(data) = self.received.pop_front() {
+                assert!(!find(&data), "Unexpected packet: {:?}", data);
+            }
+        }
+    }
+    pub fn expect_say(&mut self, server: &mut TestServer) -> String {
+        self.expect(server, |data| match data {
+            Data::Say(say) => Some(say.text.clone()),
+            _ => None,
+        })
+    }
+    /// The text of the next buffer with this name, "" is the default buffer.
+    pub fn expect_buffer(&mut self, server: &mut TestServer, name: &str) -> String {
+        self.expect(server, |data| match data {
+            Data::Buffer(buffer) if buffer.name == name => Some(buffer.text.clone()),
+            _ => None,
+        })
+    }
+    pub fn expect_error(&mut self, server: &mut TestServer) -> String {
+        self.expect(server, |data| match data {
+            Data::Error(error) => Some(error.reason.clone()),
+            _ => None,
+        })
+    }
+    pub fn create(&mut self, server: &mut TestServer, user: &str) {
+        self.send(Data::Create(packets::Create {
+            user: user.to_string(),
+            password: String::from("password"),
+            email: format!("{}@example.com", user),
+            id: format!("{}-device", user),
+        }));
+        self.expect(server, |data| match data {
+            Data::Created(_) => Some(()),
+            Data::Error(error) => panic!("Couldn't create {}: {}", user, error.reason),
+            _ => None,
+        });
+    }
+    pub fn send_login(&mut self, user: &str, password: &str) {
+        self.send(Data::Login(packets::Login {
+            user: user.to_string(),
+            password: password.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            id: format!("{}-device", user),
+            dev: None,
+        }));
+    }
+    /// Logs in and waits for the map the server sends after accepting it.
+    pub fn login(&mut self, server: &mut TestServer, user: &str) -> packets::Connected {
+        self.send_login(user, "password");
+        let connected = self.expect(server, |data| match data {
+            Data::Connected(connected) => Some(connected.clone()),
+            Data::Error(error) => panic!("Couldn't log {} in: {}", user, error.reason),
+            _ => None,
+        });
+        self.expect_map(server);
+        connected
+    }
+    pub fn expect_map(&mut self, server: &mut TestServer) -> String {
+        self.expect(server, |data| match data {
+            Data::ParseMap(parse) => Some(parse.data.clone()),
+            _ => None,
+        })
+    }
+    pub fn chat(&mut self, message: &str) {
+        self.send(Data::Chat(packets::Chat {
+            message: message.to_string(),
+        }));
+    }
+    pub fn move_to(&mut self, x: i64, y: i64, direction: i64) {
+        self.send(Data::Move(packets::Move {
+            x: Some(x),
+            y: Some(y),
+            direction: Some(direction),
+            ..Default::default()
+        }));
+    }
+}
@@ -0,0 +1,56 @@
+mod common;
+use coas_server::packets::{self, packet::Data};
+use common::TestServer;
+
+#[test]
+fn bad_packets_are_answered_and_the_server_keeps_running() {
+    let mut server = TestServer::start();
+    let mut client = server.connect();
+    client.send_raw(b"not a packet");
+    assert_eq!(
+        client.expect_error(&mut server),
+        "Something went wrong, Please try again"
+    );
+    client.create(&mut server, "alice");
+    client.send_login("alice", "bogus");
+    client.send(Data::Login(packets::Login {
+        user: String::from("alice"),
+        password: String::from("password"),
+        version: String::from("not a version"),
+        id: String::from("alice-device"),
+        dev: None,
+    }));
+    client.expect_error(&mut server);
+    assert_eq!(
+        client.expect_error(&mut server),
+        "Something went wrong, Please try again"
+    );
+    client.login(&mut server, "alice");
+    assert_eq!(server.server.packet_errors, 2);
+}
+
+#[test]
+fn players_are_told_in_chat() {
+    let mut server = TestServer::start();
+    let mut alice = server.join("alice");
+    alice.send_raw(b"not a packet");
+    assert_eq!(
+        alice.expect_say(&mut server),
+        "Something went wrong with that, It was ignored"
+    );
+    alice.send(Data::Who(packets::Who::default()));
+    alice.expect(&mut server, |data| match data {
+        Data::Buffer(_) => Some(()),
+        _ => None,
+    });
+}
+
+#[test]
+fn peers_sending_bad_packets_are_disconnected() {
+    let mut server = TestServer::start_with(|config| config.network.max_packet_errors = 3);
+    let mut client = server.connect();
+    for _ in 0..3 {
+        client.send_raw(b"not a packet");
+    }
+    client.expect_disconnect(&mut server);
+}
@@ -0,0 +1,219 @@
+//! Feeds arbitrary input through packet decoding, packet handling and commands,
+//! the server must not panic and its players must stay in a valid state.
+mod common;
+use coas_server::{
+    packets::{self, packet::Data},
+    Server,
+};
+use common::{TestClient, TestServer};
+use enet::Peer;
+use prop::{collection::vec, option, sample::select};
+use proptest::prelude::*;
+use prost::Message;
+
+const COMMANDS: &[&str] = &[
+    "help",
+    "rules",
+    "agree",
+    "hit_ping",
+    "me",
+    "admintell",
+    "grant",
+    "revoke",
+    "roles",
+    "at",
+    "notify",
+    "note",
+    "mute",
+    "silentmute",
+    "unmute",
+    "kick",
+    "ban",
+    "ipban",
+    "tempban",
+    "unban",
+    "banlist",
+    "warn",
+    "warnings",
+    "mywarnings",
+    "give",
+    "giveall",
+    "move",
+    "rawmap",
+    "rawdata",
+    "newmap",
+    "remmap",
+    "save",
+];
+
+/// A server with `fuzzer`, who can use every command, and `other` online.
+fn start() -> (TestServer, TestClient, TestClient) {
+    let mut server = TestServer::start_with_owner("fuzzer", |config| {
+        config.network.max_packet_errors = 0;
+        // Packets over a limit never reach their handlers.
+        config.limits.clear();
+    });
+    let fuzzer = server.join("fuzzer");
+    let other = server.join("other");
+    (server, fuzzer, other)
+}
+
+fn peer(server: &TestServer, name: &str) -> Option<Peer> {
+    let players = &server.server.players;
+    players.values().find(|p| p.name == name).map(|p| p.addr)
+}
+
+fn check_players(server: &Server) -> Result<(), TestCaseError> {
+    for player in server.players.values() {
+        let data = &player.data;
+        let map = server.ctx.maps.get(&data.map);
+        prop_assert!(map.is_some(), "{} is on {}", player.name, data.map);
+        let max_x = map.unwrap().max_x as isize;
+        prop_assert!(
+            (0..=max_x).contains(&data.x) && data.y >= 0,
+            "{} is at {}, {} on {}",
+            player.name,
+            data.x,
+            data.y,
+            data.map
+        );
+        prop_assert!(data.inventory.is_empty() || data.inventory.item().is_some());
+    }
+    Ok(())
+}
+
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof!["[a-z_]{0,8}", any::<String>()]
+}
+
+fn coordinate() -> impl Strategy<Value = Option<i64>> {
+    option::of(prop_oneof![4 => -2i64..24, 1 => any::<i64>()])
+}
+
+fn argument() -> impl Strategy<Value = String> {
+    prop_oneof![
+        select(vec![
+            "fuzzer",
+            "other",
+            "main",
+            "safe_zone",
+            "owner",
+            "pistol"
+        ])
+        .prop_map(String::from),
+        "-?[0-9]{1,3}",
+        "[a-z]{1,6}",
+        any::<String>(),
+    ]
+}
+
+fn command() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => (select(COMMANDS), vec(argument(), 0..5))
+            .prop_map(|(name, args)| format!("{} {}", name, args.join(" "))),
+        1 => any::<String>(),
+    ]
+}
+
+fn data() -> impl Strategy<Value = Data> {
+    prop_oneof![
+        4 => (coordinate(), coordinate(), option::of(any::<bool>()), option::of(-2i64..3))
+            .prop_map(|(x, y, silent, direction)| Data::Move(packets::Move {
+                x,
+                y,
+                silent,
+                direction,
+                ..Default::default()
+            })),
+        1 => any::<u32>().prop_map(|direction| Data::Cycle(packets::Cycle { direction })),
+        1 => text().prop_map(|weapon| Data::Draw(packets::Draw { weapon })),
+        1 => (text(), coordinate(), coordinate()).prop_map(|(sound, x, y)| Data::Play(packets::Play {
+            sound,
+            x,
+            y,
+            ..Default::default()
+        })),
+        2 => command().prop_map(|command| Data::Chat(packets::Chat {
+            message: format!("/{}", command)
+        })),
+        1 => text().prop_map(|message| Data::Chat(packets::Chat { message })),
+        1 => (text(), text(), text()).prop_map(|(user, password, version)| {
+            Data::Login(packets::Login {
+                user,
+                password,
+                version,
+                ..Default::default()
+            })
+        }),
+        1 => (text(), text()).prop_map(|(user, id)| Data::Create(packets::Create {
+            user,
+            password: String::from("password"),
+            id,
+            ..Default::default()
+        })),
+        1 => select(vec![
+            Data::Fire(packets::Fire {}),
+            Data::Reload(packets::Reload {}),
+            Data::FireStop(packets::FireStop {}),
+            Data::UseItem(packets::UseItem {}),
+            Data::Teleport(packets::Teleport {}),
+            Data::Ammo(packets::Ammo {}),
+            Data::Health(packets::Health {}),
+            Data::Who(packets::Who {}),
+            Data::Ping(packets::Ping {}),
+            Data::ServerStats(packets::ServerStats {}),
+            Data::ServerNote(packets::ServerNote {}),
+            Data::Connect(packets::Connect {}),
+            Data::Close(packets::Close {}),
+        ]),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(24))]
+
+    /// Goes through the network, so the bytes are decrypted and decoded the
+    /// way a client's would be.
+    #[test]
+    fn arbitrary_bytes(packets in vec((any::<bool>(), vec(any::<u8>(), 0..64)), 1..16)) {
+        let (mut server, mut fuzzer, _other) = start();
+        for (encrypt, bytes) in &packets {
+            match encrypt {
+                true => fuzzer.send_encrypted(bytes),
+                false => fuzzer.send_raw(bytes),
+            }
+        }
+        // Packets arrive in order, so the rest were handled once this is answered.
+        fuzzer.send(Data::Ping(packets::Ping {}));
+        fuzzer.expect(&mut server, |data| match data {
+            Data::Pong(_) => Some(()),
+            _ => None,
+        });
+        check_players(&server.server)?;
+    }
+
+    #[test]
+    fn arbitrary_packets(packets in vec((any::<bool>(), data()), 1..32)) {
+        let (mut server, _fuzzer, _other) = start();
+        let peers = [peer(&server, "fuzzer").unwrap(), peer(&server, "other").unwrap()];
+        for (by_other, data) in packets {
+            let packet = packets::Packet { data: Some(data) };
+            let peer = peers[by_other as usize];
+            // Errors are fine, they are answered and the server keeps going.
+            let _ = server.server.process_packet(&packet.encode_to_vec(), peer);
+            server.tick();
+            check_players(&server.server)?;
+        }
+    }
+
+    #[test]
+    fn arbitrary_commands(commands in vec(command(), 1..16)) {
+        let (mut server, _fuzzer, _other) = start();
+        let peer = peer(&server, "fuzzer").unwrap();
+        for command in commands {
+            let _ = server.server.process_command(&command, peer);
+            server.tick();
+            check_players(&server.server)?;
+        }
+    }
+}
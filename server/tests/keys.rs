@@ -0,0 +1,52 @@
+use clap::Parser;
+use coas_server::{cli::Cli, keys::Keys};
+use std::fs;
+
+#[test]
+fn missing_keys_are_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("keys.toml");
+    let error = Keys::load(&path).err().unwrap().to_string();
+    assert!(error.contains(&path.display().to_string()), "{}", error);
+    assert!(!path.exists());
+}
+
+#[test]
+fn generated_keys_are_random_and_never_overwritten() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("keys.toml");
+    let keys = Keys::generate();
+    assert_ne!(keys.transport, Keys::generate().transport);
+    keys.save(&path).unwrap();
+    assert!(Keys::generate().save(&path).is_err());
+    assert_eq!(Keys::load(&path).unwrap().data, keys.data);
+}
+
+#[test]
+fn import_keys_copies_existing_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("server.toml");
+    fs::write(&config, include_str!("../src/server.toml")).unwrap();
+    let old = dir.path().join("old.toml");
+    let keys = Keys::generate();
+    keys.save(&old).unwrap();
+    let path = dir.path().join("keys.toml");
+    let run = |from: &str| {
+        Cli::parse_from([
+            "coas-server".as_ref(),
+            "--config".as_ref(),
+            config.as_os_str(),
+            "--keys".as_ref(),
+            path.as_os_str(),
+            "import-keys".as_ref(),
+            dir.path().join(from).as_os_str(),
+        ])
+        .run()
+    };
+    assert!(run("missing.toml").is_err());
+    run("old.toml").unwrap();
+    let imported = Keys::load(&path).unwrap();
+    assert_eq!(imported.transport, keys.transport);
+    assert_eq!(imported.accounts, keys.accounts);
+    assert_eq!(imported.data, keys.data);
+}
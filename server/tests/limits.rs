@@ -0,0 +1,68 @@
+mod common;
+use coas_server::packets::{self, packet::Data};
+use common::TestServer;
+use std::time::Duration;
+
+/// A server where boss gets the admin alerts and limits don't refill.
+fn start() -> TestServer {
+    TestServer::start_with_owner("boss", |config| {
+        for limit in config.limits.values_mut() {
+            limit.per_second = 0.001;
+        }
+    })
+}
+
+#[test]
+fn sounds_over_the_limit_are_dropped() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    let mut alice = server.join("alice");
+    for _ in 0..8 {
+        alice.send(Data::Play(packets::Play {
+            sound: String::from("player/death.mp3"),
+            ..Default::default()
+        }));
+    }
+    // Only the burst gets through and the admins are told once.
+    let (mut plays, mut alerts) = (0, vec![]);
+    boss.refute(&mut server, Duration::from_millis(300), |data| {
+        match data {
+            Data::Play(play) if play.who == "alice" => plays += 1,
+            Data::Buffer(buffer) if buffer.text.contains("too many") => {
+                alerts.push(buffer.text.clone())
+            }
+            _ => {}
+        }
+        false
+    });
+    assert_eq!(plays, 5);
+    assert_eq!(
+        alerts,
+        ["Alert: alice (127.0.0.1) is sending too many play packets, They are being ignored"]
+    );
+}
+
+#[test]
+fn login_floods_are_disconnected() {
+    let mut server = start();
+    let mut boss = server.join("boss");
+    let mut client = server.connect();
+    client.create(&mut server, "alice");
+    for _ in 0..20 {
+        client.send_login("alice", "bogus");
+    }
+    let mut alerts = vec![];
+    boss.refute(&mut server, Duration::from_millis(300), |data| {
+        if let Data::Buffer(buffer) = data {
+            if buffer.text.contains("too many") {
+                alerts.push(buffer.text.clone());
+            }
+        }
+        false
+    });
+    assert_eq!(
+        alerts,
+        ["Alert: 127.0.0.1 was disconnected for sending too many login packets"]
+    );
+    client.expect_disconnect(&mut server);
+}
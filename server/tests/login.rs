@@ -0,0 +1,95 @@
+mod common;
+use coas_server::packets::{self, packet::Data};
+use common::TestServer;
+
+#[test]
+fn login_sends_the_map() {
+    let mut server = TestServer::start();
+    let mut client = server.connect();
+    client.create(&mut server, "alice");
+    client.send_login("alice", "password");
+    let connected = client.expect(&mut server, |data| match data {
+        Data::Connected(connected) => Some(connected.clone()),
+        _ => None,
+    });
+    assert!(connected.players.is_empty());
+    assert_eq!(connected.admin, None);
+    assert_eq!(client.expect_map(&mut server), common::MAIN_MAP);
+}
+
+#[test]
+fn wrong_password_is_rejected() {
+    let mut server = TestServer::start();
+    let mut client = server.connect();
+    client.create(&mut server, "alice");
+    client.send_login("alice", "not the password");
+    assert_eq!(
+        client.expect_error(&mut server),
+        "Invalid username or password!"
+    );
+}
+
+#[test]
+fn accounts_without_a_password_are_refused() {
+    let mut server = TestServer::start();
+    let mut client = server.connect();
+    client.create(&mut server, "alice");
+    let storage = &server.server.ctx.storage;
+    let mut account = storage.load_account("alice").unwrap().unwrap();
+    account.password_hash = None;
+    account.password = None;
+    storage.save_account("alice", &account).unwrap();
+    client.send_login("alice", "anything");
+    assert_eq!(
+        client.expect_error(&mut server),
+        "Invalid username or password!"
+    );
+}
+
+#[test]
+fn names_that_leave_the_players_directory_are_refused() {
+    let mut server = TestServer::start();
+    let mut client = server.connect();
+    client.send(Data::Create(packets::Create {
+        user: String::from("../evil"),
+        password: String::from("password"),
+        ..Default::default()
+    }));
+    assert_eq!(
+        client.expect_error(&mut server),
+        "\"../evil\" can't be used as a name"
+    );
+    assert!(!server.server.ctx.config.paths.data.join("evil").exists());
+}
+
+#[test]
+fn names_are_taken_once() {
+    let mut server = TestServer::start();
+    let _alice = server.join("alice");
+    let mut other = server.connect();
+    other.send_login("Alice", "password");
+    assert_eq!(
+        other.expect_error(&mut server),
+        "A player with the same name is already logged in!"
+    );
+}
+
+#[test]
+fn players_see_each_other_join() {
+    let mut server = TestServer::start();
+    let mut alice = server.join("alice");
+    let mut bob = server.connect();
+    bob.create(&mut server, "bob");
+    let connected = bob.login(&mut server, "bob");
+    let names = connected
+        .players
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(names, ["alice"]);
+    let position = alice.expect(&mut server, |data| match data {
+        Data::Online(online) if online.who == "bob" => Some((online.x, online.y)),
+        _ => None,
+    });
+    assert_eq!(position, (0, 0));
+}
@@ -0,0 +1,92 @@
+mod common;
+use coas_server::packets::{self, packet::Data};
+use common::TestServer;
+use std::time::Duration;
+
+#[test]
+fn moves_reach_players_on_the_map() {
+    let mut server = TestServer::start();
+    let mut alice = server.join("alice");
+    let mut bob = server.join("bob");
+    bob.move_to(1, 0, 0);
+    let (x, y) = alice.expect(&mut server, |data| match data {
+        Data::Move(m) if m.who == "bob" => Some((m.x, m.y)),
+        _ => None,
+    });
+    assert_eq!((x, y), (Some(1), Some(0)));
+}
+
+#[test]
+fn changing_maps_sends_the_players_on_the_new_map() {
+    let mut server = TestServer::start_with_owner("boss", |_| {});
+    let mut boss = server.join("boss");
+    let mut alice = server.join("alice");
+    let _bob = server.join("bob");
+    let _carol = server.join("carol");
+    boss.chat("/move carol 0 0 safe_zone");
+    alice.refute(&mut server, Duration::from_millis(200), |_| false);
+    boss.chat("/move alice 0 0 safe_zone");
+    let mut moved = vec![];
+    alice.refute(&mut server, Duration::from_millis(300), |data| {
+        if let Data::Move(m) = data {
+            moved.push(m.who.clone());
+        }
+        false
+    });
+    moved.sort();
+    assert_eq!(moved, ["alice", "carol"]);
+}
+
+#[test]
+fn moving_into_a_wall_is_corrected() {
+    let mut server = TestServer::start();
+    let mut alice = server.join("alice");
+    alice.move_to(1, 0, 0);
+    alice.expect(&mut server, |data| match data {
+        Data::Move(m) if m.who == "alice" => Some(()),
+        _ => None,
+    });
+    alice.move_to(2, 0, 0);
+    let corrected = alice.expect(&mut server, |data| match data {
+        Data::MoveClient(m) => Some(m.clone()),
+        _ => None,
+    });
+    assert_eq!((corrected.x, corrected.y), (Some(1), Some(0)));
+    assert_eq!(corrected.map, "main");
+}
+
+#[test]
+fn falling_onto_a_wall_is_allowed() {
+    let mut server = TestServer::start_with_owner("boss", |_| {});
+    let mut boss = server.join("boss");
+    let mut alice = server.join("alice");
+    boss.chat("/move alice 2 1 main");
+    alice.expect(&mut server, |data| match data {
+        Data::MoveClient(_) => Some(()),
+        _ => None,
+    });
+    alice.send(Data::Move(packets::Move {
+        x: Some(2),
+        y: Some(0),
+        silent: Some(true),
+        ..Default::default()
+    }));
+    let landed = alice.expect(&mut server, |data| match data {
+        Data::Move(m) if m.who == "alice" && m.y == Some(0) => Some(m.x),
+        Data::MoveClient(m) => panic!("the fall was corrected to {:?}, {:?}", m.x, m.y),
+        _ => None,
+    });
+    assert_eq!(landed, Some(2));
+}
+
+#[test]
+fn moving_more_than_a_tile_is_corrected() {
+    let mut server = TestServer::start();
+    let mut alice = server.join("alice");
+    alice.move_to(5, 0, 0);
+    let corrected = alice.expect(&mut server, |data| match data {
+        Data::MoveClient(m) => Some(m.clone()),
+        _ => None,
+    });
+    assert_eq!((corrected.x, corrected.y), (Some(0), Some(0)));
+}
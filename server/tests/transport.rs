@@ -0,0 +1,50 @@
+mod common;
+use coas_server::packets::{self, packet::Data};
+use common::TestServer;
+
+#[test]
+fn session_and_fernet_clients_play_together() {
+    let mut server = TestServer::start();
+    let mut client = server.join("alice");
+    let mut old = server.connect_fernet();
+    old.create(&mut server, "bob");
+    old.login(&mut server, "bob");
+    client.chat("/agree");
+    client.expect_say(&mut server);
+    client.chat("hello");
+    old.expect(&mut server, |data| match data {
+        Data::Chat(chat) if chat.message == "alice says: hello" => Some(()),
+        _ => None,
+    });
+    client.expect(&mut server, |data| match data {
+        Data::Chat(_) => Some(()),
+        _ => None,
+    });
+}
+
+#[test]
+fn replayed_packets_are_refused() {
+    let mut server = TestServer::start();
+    let mut client = server.join("alice");
+    client.send(Data::Ping(packets::Ping {}));
+    client.expect(&mut server, |data| match data {
+        Data::Pong(_) => Some(()),
+        _ => None,
+    });
+    let errors = server.server.packet_errors;
+    client.resend_last();
+    client.expect_say(&mut server);
+    assert_eq!(server.server.packet_errors, errors + 1);
+}
+
+#[test]
+fn fernet_clients_are_told_to_update_when_turned_off() {
+    let mut server = TestServer::start_with(|config| config.network.fernet_transport = false);
+    let mut old = server.connect_fernet();
+    old.send_login("alice", "password");
+    assert!(old.expect_error(&mut server).contains("newer version"));
+    old.expect_disconnect(&mut server);
+    let mut client = server.connect();
+    client.create(&mut server, "alice");
+    client.login(&mut server, "alice");
+}